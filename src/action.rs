@@ -1,7 +1,7 @@
 use rand::Rng;
 use rand::distributions::{Distribution, Standard};
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Action {
     DIGEST,
     SEED,
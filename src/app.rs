@@ -1,12 +1,24 @@
 use futures::lock::Mutex;
 use glutin_window::GlutinWindow;
-use graphics::{clear, rectangle, text, Transformed};
+use graphics::{clear, line, rectangle, text, Rectangle, Transformed};
 use opengl_graphics::{GlGraphics, GlyphCache};
-use piston::input::{RenderArgs, UpdateArgs};
+use piston::input::{Key, MouseButton, RenderArgs, UpdateArgs};
+use piston::window::Window;
+use rand::prelude::*;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use crate::camera::Camera;
+use crate::capture::Capture;
+use crate::common::coord::Coord;
+use crate::common::dna::SIZE;
+use crate::edit::{Edit, Editor, Tool};
+use crate::hud;
+use crate::inspector::pixel_to_coord;
+use crate::layer::Layer;
 use crate::scene::Scene;
+use crate::stats::{Recorder, Sample};
+use crate::stepper::Driver;
 
 pub struct App {
     pub gl: GlGraphics,
@@ -16,16 +28,93 @@ pub struct App {
     renders: Vec<u128>,
     render_sum: u128,
     glyph_cache_mutex: Mutex<GlyphCache<'static>>,
+    // drives the world from `update` when running the step engine
+    stepper: Option<Box<dyn Driver>>,
+    recorder: Recorder,
+    capture: Option<Capture>,
+    // whether the HUD panel is shown
+    hud: bool,
+    camera: Camera,
+    layer: Layer,
+    // last known mouse position in window pixels, and the cell being inspected
+    cursor: [f64; 2],
+    selected: Option<Coord>,
+    // where the left button went down, and whether it has moved far enough
+    // since to be a drag rather than a click
+    pressed: Option<[f64; 2]>,
+    dragged: bool,
+    // the painting tools: the brush and its radius in cells, the genome the
+    // inject brush paints with and where it came from (None: a random one
+    // per stroke, drawn from `rng`, which descends from the run's seed), the
+    // tool of the stroke in progress and the cell it last dabbed, and dabs
+    // waiting for `update` to apply them
+    editor: Editor,
+    brush: Brush,
+    radius: i32,
+    genome: Option<([u64; SIZE], &'static str)>,
+    rng: StdRng,
+    stroke: Option<Tool>,
+    dabbed: Option<Coord>,
+    pending: Vec<Edit>,
 }
 
+/// What the left mouse button does, chosen with the number keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Brush {
+    /// Click to inspect, drag to pan.
+    Inspect,
+    Kill,
+    Inject,
+    Feed,
+    Drain,
+    Wall,
+    Erase,
+}
+
+impl Brush {
+    fn name(self) -> &'static str {
+        match self {
+            Brush::Inspect => "inspect",
+            Brush::Kill => "kill",
+            Brush::Inject => "inject",
+            Brush::Feed => "feed",
+            Brush::Drain => "drain",
+            Brush::Wall => "wall",
+            Brush::Erase => "erase wall",
+        }
+    }
+}
+
+// The energy a feed or drain dab adds to or takes from each cell.
+const BRUSH_ENERGY: f32 = 0.25;
+
+// The largest brush radius, in cells.
+const MAX_RADIUS: i32 = 32;
+
+// How far the pointer may wander, in window pixels, before a click is a drag.
+const DRAG_THRESHOLD: f64 = 3.0;
+
 impl App {
+    #[allow(clippy::too_many_arguments)] // one per thing the viewer can be wired to
     pub fn new(
         scene: Arc<Scene>,
         window: GlutinWindow,
         gl: GlGraphics,
         glyph_cache_mutex: Mutex<GlyphCache<'static>>,
+        stepper: Option<Box<dyn Driver>>,
+        recorder: Recorder,
+        capture: Option<Capture>,
+        editor: Editor,
+        rng: StdRng,
     ) -> App {
         let renders: Vec<u128> = Vec::new();
+        let size = window.size();
+        let mut camera = Camera::new([size.width, size.height]);
+        // start with the whole world in view if it doesn't fit at 1:1
+        let world = scene.world_pixels();
+        if world[0] > size.width || world[1] > size.height {
+            camera.fit(world[0], world[1]);
+        }
         App {
             scene,
             gl,
@@ -34,24 +123,55 @@ impl App {
             renders,
             render_sum: 0,
             glyph_cache_mutex,
+            stepper,
+            recorder,
+            capture,
+            hud: true,
+            camera,
+            layer: Layer::default(),
+            cursor: [0.0, 0.0],
+            selected: None,
+            pressed: None,
+            dragged: false,
+            editor,
+            brush: Brush::Inspect,
+            radius: 2,
+            genome: None,
+            rng,
+            stroke: None,
+            dabbed: None,
+            pending: Vec::new(),
         }
     }
 
+    /// Have the inject brush paint `seq`, described in the status line as
+    /// coming from `source`.
+    pub fn inject_with(&mut self, seq: [u64; SIZE], source: &'static str) {
+        self.genome = Some((seq, source));
+    }
+
     pub async fn render(&mut self, args: &RenderArgs) {
         // capture instants first for timings
         let millis_since = self.last_render.elapsed().as_millis();
         self.last_render = Instant::now();
 
         // snapshot the world, then clear + draw every dot in a SINGLE gl.draw()
-        // so the whole frame is one OpenGL flush instead of one per dot
-        let dots = self.scene.describe();
+        // so the whole frame is one OpenGL flush instead of one per dot; dots
+        // the camera can't see are culled
+        self.camera.resize(args.window_size);
+        let camera = self.camera;
+        let dots = self.scene.describe_as(self.layer);
         self.gl.draw(args.viewport(), |c, gl| {
             clear([0.0, 0.0, 0.0, 1.0], gl);
+            let transform = world_transform(&camera, c.transform);
             for (x, y, sz, ([r, g, b], opc)) in &dots {
+                if !camera.visible(*x, *y, *sz) {
+                    continue;
+                }
                 rectangle(
                     [*r, *g, *b, *opc],
                     rectangle::centered_square(*x, *y, *sz),
-                    c.transform,
+                    transform,
                     gl,
                 );
             }
@@ -68,13 +188,38 @@ impl App {
             fps = 1000 / (self.render_sum / frame_average_count as u128);
         }
 
-        // render fps
+        if let Some(pos) = self.selected {
+            self.render_inspector(args, pos).await;
+        }
+        if self.hud {
+            self.render_hud(args);
+        }
+
+        // render fps, the clock when it isn't running at normal speed and
+        // the layer when it isn't the default
+        let clock = &self.scene.clock;
+        let mut status = if clock.paused() {
+            " paused".to_string()
+        } else if clock.scale() != 1.0 {
+            format!(" {:.2}x", clock.scale())
+        } else {
+            String::new()
+        };
+        if self.layer != Layer::default() {
+            status += &format!(" {}", self.layer.name());
+        }
+        if self.brush != Brush::Inspect {
+            status += &format!(" {} r{}", self.brush.name(), self.radius);
+        }
+        if self.brush == Brush::Inject {
+            status += &format!(" ({})", self.genome.map_or("random", |(_, source)| source));
+        }
         let glyph_cache = self.glyph_cache_mutex.get_mut();
         self.gl.draw(args.viewport(), |c, gl| {
             text(
                 [1.0, 0.0, 0.0, 1.0],
                 32,
-                &format!("{}", fps),
+                &format!("{}{}", fps, status),
                 glyph_cache,
                 c.transform.trans(10.0, 42.0),
                 gl,
@@ -83,5 +228,263 @@ impl App {
         });
     }
 
-    pub async fn update(&mut self, _args: &UpdateArgs) {}
+    // Outline the selected cell and list what the inspector knows about it
+    // in a panel under the FPS counter.
+    async fn render_inspector(&mut self, args: &RenderArgs, pos: Coord) {
+        let Some(cell) = self.scene.at(pos) else {
+            return;
+        };
+        let lines = cell.dot.lock().await.inspect().lines();
+        let scale = self.scene.config().world.scale as f64;
+        let glyph_cache = self.glyph_cache_mutex.get_mut();
+        let (size, line_height) = (14, 16.0);
+        let camera = self.camera;
+        self.gl.draw(args.viewport(), |c, gl| {
+            // a border one window pixel wide, whatever the zoom
+            Rectangle::new_border([1.0, 1.0, 0.0, 1.0], 1.0 / camera.zoom).draw(
+                [pos.x as f64 * scale, pos.y as f64 * scale, scale, scale],
+                &c.draw_state,
+                world_transform(&camera, c.transform),
+                gl,
+            );
+            let longest = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+            let width = 8.0 + longest as f64 * size as f64 * 0.5;
+            let height = 8.0 + lines.len() as f64 * line_height;
+            rectangle([0.0, 0.0, 0.0, 0.75], [10.0, 52.0, width, height], c.transform, gl);
+            for (i, line) in lines.iter().enumerate() {
+                let y = 52.0 + line_height * (i + 1) as f64;
+                text([1.0; 4], size, line, glyph_cache, c.transform.trans(14.0, y), gl).unwrap();
+            }
+        });
+    }
+
+    // The recorder's latest figures, and scrolling graphs of population and
+    // species over its history, in a panel at the bottom-left corner.
+    fn render_hud(&mut self, args: &RenderArgs) {
+        let history = self.recorder.history();
+        let lines = hud::lines(history);
+        if lines.is_empty() {
+            return;
+        }
+        let series = |f: fn(&Sample) -> usize| -> Vec<f64> {
+            history.iter().map(|s| f(s) as f64).collect()
+        };
+        let graphs = [
+            ("population", [0.3, 0.9, 0.4, 1.0], series(|s| s.population)),
+            ("species", [1.0, 0.6, 0.2, 1.0], series(|s| s.species)),
+        ];
+        let (size, line_height, graph_height, width) = (14, 16.0, 48.0, 300.0);
+        let height = 8.0
+            + (lines.len() + graphs.len()) as f64 * line_height
+            + graphs.len() as f64 * (graph_height + 8.0);
+        let top = args.window_size[1] - height - 10.0;
+        let glyph_cache = self.glyph_cache_mutex.get_mut();
+        self.gl.draw(args.viewport(), |c, gl| {
+            rectangle([0.0, 0.0, 0.0, 0.75], [10.0, top, width + 8.0, height], c.transform, gl);
+            let mut y = top;
+            for l in &lines {
+                y += line_height;
+                text([1.0; 4], size, l, glyph_cache, c.transform.trans(14.0, y), gl).unwrap();
+            }
+            for (label, color, values) in &graphs {
+                y += line_height + 8.0;
+                let max = values.iter().cloned().fold(0.0, f64::max);
+                let caption = format!("{} (max {})", label, max);
+                text(*color, size, &caption, glyph_cache, c.transform.trans(14.0, y), gl).unwrap();
+                let origin = c.transform.trans(14.0, y + 4.0);
+                for pair in hud::plot(values, width, graph_height).windows(2) {
+                    let [[x0, y0], [x1, y1]] = [pair[0], pair[1]];
+                    line(*color, 0.75, [x0, y0, x1, y1], origin, gl);
+                }
+                y += graph_height;
+            }
+        });
+    }
+
+    /// Follow the pointer, painting while a stroke is in progress and
+    /// otherwise panning the camera while the left button drags.
+    pub fn cursor(&mut self, pos: [f64; 2]) {
+        if self.stroke.is_some() {
+            self.cursor = pos;
+            self.dab();
+            return;
+        }
+        if let Some(from) = self.pressed {
+            self.dragged |= (pos[0] - from[0]).hypot(pos[1] - from[1]) > DRAG_THRESHOLD;
+            if self.dragged {
+                self.camera.pan([pos[0] - self.cursor[0], pos[1] - self.cursor[1]]);
+            }
+        }
+        self.cursor = pos;
+    }
+
+    /// The left button starts a stroke of the brush, or with the inspect
+    /// brush a drag or a click; a right click closes the inspector.
+    pub fn press(&mut self, button: MouseButton) {
+        let amount = BRUSH_ENERGY;
+        let tool = match self.brush {
+            Brush::Inspect => None,
+            Brush::Kill => Some(Tool::Kill),
+            Brush::Inject => {
+                let seq = self.genome.map_or_else(|| self.rng.gen(), |(seq, _)| seq);
+                Some(Tool::Inject { seq })
+            }
+            Brush::Feed => Some(Tool::Energy { amount }),
+            Brush::Drain => Some(Tool::Energy { amount: -amount }),
+            Brush::Wall => Some(Tool::Wall { solid: true }),
+            Brush::Erase => Some(Tool::Wall { solid: false }),
+        };
+        match button {
+            MouseButton::Left if tool.is_some() => {
+                self.stroke = tool;
+                self.dab();
+            }
+            MouseButton::Left => (self.pressed, self.dragged) = (Some(self.cursor), false),
+            MouseButton::Right => self.selected = None,
+            _ => {}
+        }
+    }
+
+    /// Releasing the left button without dragging inspects the cell under
+    /// the cursor, or closes the inspector off the world.
+    pub fn release(&mut self, button: MouseButton) {
+        if button == MouseButton::Left && self.stroke.take().is_some() {
+            self.dabbed = None;
+            return;
+        }
+        if button == MouseButton::Left && self.pressed.take().is_some() && !self.dragged {
+            let [x, y] = self.camera.to_world(self.cursor);
+            let scale = self.scene.config().world.scale as f64;
+            self.selected = pixel_to_coord(x, y, scale, self.scene.size());
+        }
+    }
+
+    // Queue the stroke's tool on the cell under the cursor, once per cell
+    // the stroke passes over.
+    fn dab(&mut self) {
+        let Some(tool) = self.stroke else {
+            return;
+        };
+        let [x, y] = self.camera.to_world(self.cursor);
+        let scale = self.scene.config().world.scale as f64;
+        let Some(center) = pixel_to_coord(x, y, scale, self.scene.size()) else {
+            return;
+        };
+        if self.dabbed != Some(center) {
+            self.dabbed = Some(center);
+            // stamped with the simulation time when `update` applies it
+            self.pending.push(Edit { time_ms: 0, center, radius: self.radius, tool });
+        }
+    }
+
+    /// Zoom in or out around the cursor, a step per notch of the wheel.
+    pub fn scroll(&mut self, notches: f64) {
+        self.camera.zoom_at(1.25f64.powf(notches), self.cursor);
+    }
+
+    /// Clock controls: Space pauses and resumes, `.` steps once while paused,
+    /// `+`/`-` double or halve the speed and `0` restores normal speed.
+    /// `L` switches to the next visualisation layer and `H` shows or hides
+    /// the HUD.
+    /// Camera controls: the arrow keys pan, `F` fits the world to the window
+    /// and `Home` returns to 1:1 at the top-left corner.
+    /// Painting: `1` to `7` pick the inspect, kill, inject, feed, drain, wall
+    /// and erase-wall brushes, `[`/`]` shrink and grow the brush, `C` has
+    /// the inject brush copy the inspected cell's genome (also printed in the
+    /// text format, for `dots decode`) and `G` go back to random genomes.
+    pub async fn key(&mut self, key: Key) {
+        let clock = &self.scene.clock;
+        let [w, h] = self.camera.viewport.map(|v| v / 10.0);
+        match key {
+            Key::Left => self.camera.pan([w, 0.0]),
+            Key::Right => self.camera.pan([-w, 0.0]),
+            Key::Up => self.camera.pan([0.0, h]),
+            Key::Down => self.camera.pan([0.0, -h]),
+            Key::F => {
+                let [width, height] = self.scene.world_pixels();
+                self.camera.fit(width, height);
+            }
+            Key::Home => self.camera = Camera::new(self.camera.viewport),
+            Key::H => self.hud = !self.hud,
+            Key::L => self.layer = self.layer.next(),
+            Key::Space if clock.paused() => clock.resume(),
+            Key::Space => clock.pause(),
+            Key::Period if clock.paused() => match &mut self.stepper {
+                Some(stepper) => {
+                    stepper.step();
+                }
+                None => clock.step(Duration::from_millis(self.scene.config().engine.step_ms)),
+            },
+            Key::Equals | Key::Plus | Key::NumPadPlus => {
+                clock.set_scale(clock.scale() * 2.0);
+            }
+            Key::Minus | Key::NumPadMinus => {
+                clock.set_scale(clock.scale() / 2.0);
+            }
+            Key::D0 | Key::NumPad0 => {
+                clock.set_scale(1.0);
+            }
+            Key::D1 => self.brush = Brush::Inspect,
+            Key::D2 => self.brush = Brush::Kill,
+            Key::D3 => self.brush = Brush::Inject,
+            Key::D4 => self.brush = Brush::Feed,
+            Key::D5 => self.brush = Brush::Drain,
+            Key::D6 => self.brush = Brush::Wall,
+            Key::D7 => self.brush = Brush::Erase,
+            Key::LeftBracket => self.radius = (self.radius - 1).max(0),
+            Key::RightBracket => self.radius = (self.radius + 1).min(MAX_RADIUS),
+            Key::C => {
+                let cell = self.selected.and_then(|pos| self.scene.at(pos));
+                if let Some(cell) = cell {
+                    if let Some(dna) = cell.dot.lock().await.dna {
+                        self.genome = Some((dna.seq, "copied"));
+                        println!("{}", dna.format());
+                    }
+                }
+            }
+            Key::G => self.genome = None,
+            _ => {}
+        }
+    }
+
+    /// Simulation time: the engine's own under the step engines, the clock's
+    /// under the async one.
+    pub fn now_ms(&self) -> u64 {
+        match &self.stepper {
+            Some(stepper) => stepper.now(),
+            None => self.scene.clock.now().as_millis() as u64,
+        }
+    }
+
+    pub async fn update(&mut self, args: &UpdateArgs) {
+        let clock = &self.scene.clock;
+        if let Some(stepper) = &mut self.stepper {
+            if !clock.paused() {
+                stepper.advance((args.dt * 1000.0 * clock.scale()).round() as u64);
+            }
+        }
+
+        let now_ms = self.now_ms();
+        for edit in std::mem::take(&mut self.pending) {
+            let edit = Edit { time_ms: now_ms, ..edit };
+            if let Err(e) = self.editor.apply(edit, &self.scene).await {
+                eprintln!("edits: {}", e);
+            }
+        }
+        if let Err(e) = self.editor.poll(&self.scene, now_ms).await {
+            eprintln!("edits: {}", e);
+        }
+        if let Err(e) = self.recorder.poll(&self.scene, now_ms).await {
+            eprintln!("stats: {}", e);
+        }
+        if let Some(Err(e)) = self.capture.as_mut().map(|c| c.poll(&self.scene)) {
+            eprintln!("capture: {}", e);
+            self.capture = None;
+        }
+    }
+}
+
+// Draw in world pixels through the camera: window = (world - offset) * zoom.
+fn world_transform(camera: &Camera, window: graphics::math::Matrix2d) -> graphics::math::Matrix2d {
+    window.zoom(camera.zoom).trans(-camera.offset[0], -camera.offset[1])
 }
@@ -0,0 +1,89 @@
+use rand::prelude::*;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::bus::Bus;
+use crate::common::coord::Coord;
+use crate::common::direction::Direction;
+use crate::config::Config;
+use crate::dots::DotFactory;
+use crate::grid::Backend;
+use crate::scene::Scene;
+
+const SIZES: [(u32, u32); 3] = [(320, 180), (640, 360), (1280, 720)];
+
+/// Compare the storage backends on what the simulation does most: a full
+/// neighbourhood sense per cell (as `Dot::tick` does), a lookup per cell (as
+/// effect delivery does) and a whole-scene `describe`. Prints one row per
+/// backend and grid size.
+pub fn run(config: &Config) {
+    println!(
+        "{:<7} {:>10} {:>12} {:>12} {:>13}",
+        "backend", "grid", "sense ns", "at ns", "describe ms"
+    );
+    for (width, height) in SIZES {
+        for backend in [Backend::Dense, Backend::Map] {
+            let mut config = config.clone();
+            (config.world.width, config.world.height) = (width, height);
+            config.world.storage = backend;
+            let scene = filled(config);
+            let cells = width as usize * height as usize;
+            let positions: Vec<Coord> =
+                (0..cells).map(|i| Coord::from_index(i, scene.size())).collect();
+            let steps: Vec<(i32, i32)> =
+                (0..8).map(|i| Direction::from_index(i).offset()).collect();
+
+            let sense = time(cells * 8, || {
+                let mut acc = 0u32;
+                for pos in &positions {
+                    for &(dx, dy) in &steps {
+                        acc ^= scene.sense(Coord::new(pos.x + dx, pos.y + dy));
+                    }
+                }
+                black_box(acc);
+            });
+            let at = time(cells, || {
+                for pos in &positions {
+                    black_box(scene.at(*pos));
+                }
+            });
+            let describe = time(1, || {
+                black_box(scene.describe());
+            });
+            println!(
+                "{:<7} {:>10} {:>12.1} {:>12.1} {:>13.2}",
+                format!("{:?}", backend).to_lowercase(),
+                format!("{}x{}", width, height),
+                sense,
+                at,
+                describe / 1e6
+            );
+        }
+    }
+}
+
+fn filled(config: Config) -> Arc<Scene> {
+    let scene = Arc::new(Scene::new(Arc::new(config)));
+    let bus = Arc::new(Bus::unbounded());
+    let factory = DotFactory::new(bus, scene.clone(), StdRng::seed_from_u64(0));
+    let size = scene.size();
+    for i in 0..size.x as usize * size.y as usize {
+        let pos = Coord::from_index(i, size);
+        scene.push_dot(pos, factory.build(pos, None, 1.0));
+    }
+    scene
+}
+
+// Mean nanoseconds per operation, over enough repetitions of `f` (which does
+// `ops` operations) to fill about half a second, after one warm-up call.
+fn time(ops: usize, mut f: impl FnMut()) -> f64 {
+    f();
+    let start = Instant::now();
+    let mut reps = 0;
+    while reps == 0 || start.elapsed().as_millis() < 500 {
+        f();
+        reps += 1;
+    }
+    start.elapsed().as_nanos() as f64 / (reps * ops) as f64
+}
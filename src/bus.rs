@@ -0,0 +1,184 @@
+use flume::{Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::common::coord::Coord;
+use crate::effect::Effect;
+
+/// An effect on its way to the cell at `Coord`.
+pub type Delivery = (Coord, Arc<Effect>);
+
+/// What a send does when the bus is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Wait for room: senders slow down to the propagators' pace.
+    #[default]
+    Block,
+    /// Evict the longest-queued effect to make room.
+    DropOldest,
+    /// Discard the effect being sent.
+    DropNewest,
+}
+
+/// Running totals since the bus was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Effects waiting right now.
+    pub depth: usize,
+    pub sent: u64,
+    /// Effects taken off the bus by an engine or propagator.
+    pub delivered: u64,
+    /// Effects discarded by the overflow policy.
+    pub dropped: u64,
+    /// Summed time delivered effects spent queued, in microseconds.
+    pub latency_us: u64,
+}
+
+/// The effect channel between dots and whatever applies their effects. The
+/// bus holds both ends, so it never disconnects and a send never fails — a
+/// full bus applies its `Overflow` policy instead.
+pub struct Bus {
+    tx: Sender<(Coord, Arc<Effect>, Instant)>,
+    rx: Receiver<(Coord, Arc<Effect>, Instant)>,
+    policy: Overflow,
+    sent: AtomicU64,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    latency_us: AtomicU64,
+}
+
+impl Bus {
+    /// A bus holding at most `capacity` effects, or any number if None.
+    pub fn new(capacity: Option<usize>, policy: Overflow) -> Bus {
+        let (tx, rx) = match capacity {
+            Some(n) => flume::bounded(n),
+            None => flume::unbounded(),
+        };
+        Bus {
+            tx,
+            rx,
+            policy,
+            sent: AtomicU64::new(0),
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            latency_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn unbounded() -> Bus {
+        Bus::new(None, Overflow::Block)
+    }
+
+    /// Send, waiting for room if the bus is full and blocking.
+    pub async fn send(&self, delivery: Delivery) {
+        if let Some((pos, effect)) = self.offer(delivery) {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+            // can't fail: the bus holds a receiver
+            let _ = self.tx.send_async((pos, effect, Instant::now())).await;
+        }
+    }
+
+    /// Send without waiting. A full bus applies its policy, except that a
+    /// blocking one hands the effect back — for the propagators, which must
+    /// never wait on the bus they drain.
+    pub fn offer(&self, (pos, effect): Delivery) -> Option<Delivery> {
+        let mut item = (pos, effect, Instant::now());
+        loop {
+            match self.tx.try_send(item) {
+                Ok(()) => {
+                    self.sent.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                Err(TrySendError::Full(back)) => item = back,
+                // unreachable: the bus holds a receiver
+                Err(TrySendError::Disconnected(_)) => return None,
+            }
+            match self.policy {
+                Overflow::Block => return Some((item.0, item.1)),
+                Overflow::DropNewest => {
+                    self.sent.fetch_add(1, Ordering::Relaxed);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                Overflow::DropOldest => {
+                    if self.rx.try_recv().is_ok() {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for the next effect.
+    pub async fn recv(&self) -> Delivery {
+        // the bus holds a sender, so this can't fail
+        let item = self.rx.recv_async().await.expect("the bus holds a sender");
+        self.deliver(item)
+    }
+
+    /// Take every effect queued right now.
+    pub fn drain(&self) -> Vec<Delivery> {
+        self.rx.drain().map(|item| self.deliver(item)).collect()
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            depth: self.rx.len(),
+            sent: self.sent.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            latency_us: self.latency_us.load(Ordering::Relaxed),
+        }
+    }
+
+    fn deliver(&self, (pos, effect, sent): (Coord, Arc<Effect>, Instant)) -> Delivery {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+        self.latency_us
+            .fetch_add(sent.elapsed().as_micros() as u64, Ordering::Relaxed);
+        (pos, effect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(n: i32) -> Delivery {
+        (Coord::new(n, 0), Arc::new(Effect::ENERGY(0.0, None, None)))
+    }
+
+    fn xs(items: Vec<Delivery>) -> Vec<i32> {
+        items.into_iter().map(|(pos, _)| pos.x).collect()
+    }
+
+    #[tokio::test]
+    async fn overflow_policies() {
+        let newest = Bus::new(Some(2), Overflow::DropNewest);
+        let oldest = Bus::new(Some(2), Overflow::DropOldest);
+        for n in 0..4 {
+            newest.send(effect(n)).await;
+            oldest.send(effect(n)).await;
+        }
+        assert_eq!(newest.metrics().depth, 2);
+        assert_eq!(xs(newest.drain()), vec![0, 1]);
+        assert_eq!(xs(oldest.drain()), vec![2, 3]);
+        let m = oldest.metrics();
+        assert_eq!((m.depth, m.sent, m.delivered, m.dropped), (0, 4, 2, 2));
+
+        // a blocked sender goes through once there is room
+        let block = Arc::new(Bus::new(Some(1), Overflow::Block));
+        block.send(effect(0)).await;
+        assert!(block.offer(effect(9)).is_some(), "a full blocking bus hands effects back");
+        let sender = tokio::spawn({
+            let block = block.clone();
+            async move { block.send(effect(1)).await }
+        });
+        assert_eq!(block.recv().await.0.x, 0);
+        sender.await.unwrap();
+        assert_eq!(xs(block.drain()), vec![1]);
+        assert_eq!(block.metrics().dropped, 0);
+    }
+}
@@ -0,0 +1,113 @@
+/// How far the camera can zoom out and in, as window pixels per world pixel.
+pub const MIN_ZOOM: f64 = 0.05;
+pub const MAX_ZOOM: f64 = 64.0;
+
+/// Maps world pixels (the coordinates `Scene::describe` gives sprites, a cell
+/// being `world.scale` of them across) to window pixels and back:
+/// `window = (world - offset) * zoom`. Knows nothing of the windowing
+/// library, so it can be driven and tested on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// The world pixel at the window's top-left corner.
+    pub offset: [f64; 2],
+    pub zoom: f64,
+    /// The window's size in pixels.
+    pub viewport: [f64; 2],
+}
+
+impl Camera {
+    /// A camera showing the world at 1:1 from its top-left corner.
+    pub fn new(viewport: [f64; 2]) -> Camera {
+        Camera {
+            offset: [0.0, 0.0],
+            zoom: 1.0,
+            viewport,
+        }
+    }
+
+    pub fn to_window(self, [x, y]: [f64; 2]) -> [f64; 2] {
+        [(x - self.offset[0]) * self.zoom, (y - self.offset[1]) * self.zoom]
+    }
+
+    pub fn to_world(self, [x, y]: [f64; 2]) -> [f64; 2] {
+        [x / self.zoom + self.offset[0], y / self.zoom + self.offset[1]]
+    }
+
+    /// Move the view by a window-pixel delta, as a drag would: the world
+    /// follows the pointer.
+    pub fn pan(&mut self, [dx, dy]: [f64; 2]) {
+        self.offset[0] -= dx / self.zoom;
+        self.offset[1] -= dy / self.zoom;
+    }
+
+    /// Zoom by `factor`, keeping the world point under window pixel `anchor`
+    /// where it is.
+    pub fn zoom_at(&mut self, factor: f64, anchor: [f64; 2]) {
+        let fixed = self.to_world(anchor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = [
+            fixed[0] - anchor[0] / self.zoom,
+            fixed[1] - anchor[1] / self.zoom,
+        ];
+    }
+
+    /// Zoom and centre so the whole `width` x `height` world fits the window.
+    pub fn fit(&mut self, width: f64, height: f64) {
+        self.zoom = (self.viewport[0] / width)
+            .min(self.viewport[1] / height)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = [
+            (width - self.viewport[0] / self.zoom) / 2.0,
+            (height - self.viewport[1] / self.zoom) / 2.0,
+        ];
+    }
+
+    /// The window was resized; the view keeps its top-left corner and zoom.
+    pub fn resize(&mut self, viewport: [f64; 2]) {
+        self.viewport = viewport;
+    }
+
+    /// Whether any of the square of half-size `half` centred on world pixel
+    /// (`x`, `y`) lands in the window, so anything else can be culled.
+    pub fn visible(&self, x: f64, y: f64, half: f64) -> bool {
+        let [left, top] = self.to_window([x - half, y - half]);
+        let [right, bottom] = self.to_window([x + half, y + half]);
+        right > 0.0 && bottom > 0.0 && left < self.viewport[0] && top < self.viewport[1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pan_zoom_fit_and_cull() {
+        let mut camera = Camera::new([200.0, 100.0]);
+        camera.pan([-50.0, -20.0]);
+        assert_eq!(camera.offset, [50.0, 20.0]);
+        assert_eq!(camera.to_window([60.0, 30.0]), [10.0, 10.0]);
+
+        // the point under the anchor stays put
+        let anchor = [40.0, 30.0];
+        let under = camera.to_world(anchor);
+        camera.zoom_at(4.0, anchor);
+        assert_eq!(camera.zoom, 4.0);
+        assert_eq!(camera.to_world(anchor), under);
+        assert_eq!(camera.to_window(under), anchor);
+        camera.pan([8.0, 0.0]);
+        assert_eq!(camera.to_world(anchor)[0], under[0] - 2.0);
+        camera.zoom_at(1e6, anchor);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+
+        // a 1000x250 world is limited by its width: zoom 0.2, centred vertically
+        camera.fit(1000.0, 250.0);
+        assert_eq!(camera.zoom, 0.2);
+        assert_eq!(camera.to_window([0.0, 0.0]), [0.0, 25.0]);
+        assert_eq!(camera.to_window([1000.0, 250.0]), [200.0, 75.0]);
+
+        assert!(camera.visible(500.0, 125.0, 1.0));
+        assert!(camera.visible(-0.5, 0.0, 1.0), "partly in view");
+        assert!(!camera.visible(-2.0, 0.0, 1.0));
+        assert!(!camera.visible(500.0, 400.0, 1.0));
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::scene::{Scene, Sprite};
+
+/// The most memory one frame may take; a world whose frames would take more
+/// can't be captured at its scale.
+const MAX_FRAME_BYTES: usize = 1 << 30;
+
+// A frame's RGBA byte count, if it is within `MAX_FRAME_BYTES`.
+fn frame_bytes(width: u32, height: u32) -> Option<usize> {
+    let bytes = (width as usize).checked_mul(height as usize)?.checked_mul(4)?;
+    (bytes <= MAX_FRAME_BYTES).then_some(bytes)
+}
+
+// The scene's frame size in pixels, or an error if it is too large to capture.
+fn frame_size(scene: &Scene) -> Result<(u32, u32), String> {
+    let size = scene.size();
+    let scale = scene.config().world.scale as u32;
+    let (width, height) = (size.x as u32 * scale, size.y as u32 * scale);
+    match frame_bytes(width, height) {
+        Some(_) => Ok((width, height)),
+        None => Err(format!("a {}x{} frame is too large to capture", width, height)),
+    }
+}
+
+/// An RGBA image of the scene, drawn in software so frames can be captured
+/// without a window or GPU.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major RGBA, 4 bytes per pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// The scene at its configured `scale`, as the window would show it.
+    pub fn render(scene: &Scene) -> Result<Frame, String> {
+        let (width, height) = frame_size(scene)?;
+        Ok(Frame::rasterize(width, height, &scene.describe()))
+    }
+
+    /// Fill each sprite's square over a black background, blending by its
+    /// opacity the way `App::render` does on the GPU. Panics if the frame
+    /// would take more than `MAX_FRAME_BYTES`.
+    pub fn rasterize(width: u32, height: u32, sprites: &[Sprite]) -> Frame {
+        let bytes = frame_bytes(width, height).expect("frame too large to rasterize");
+        let mut pixels = vec![0u8; bytes];
+        for chunk in pixels.chunks_exact_mut(4) {
+            chunk[3] = 0xff;
+        }
+        for &(x, y, sz, ([r, g, b], opc)) in sprites {
+            let x0 = (x - sz).round().clamp(0.0, width as f64) as usize;
+            let x1 = (x + sz).round().clamp(0.0, width as f64) as usize;
+            let y0 = (y - sz).round().clamp(0.0, height as f64) as usize;
+            let y1 = (y + sz).round().clamp(0.0, height as f64) as usize;
+            let rgb = [r, g, b].map(|c| (c * opc).clamp(0.0, 1.0));
+            for row in y0..y1 {
+                let start = (row * width as usize + x0) * 4;
+                let end = (row * width as usize + x1) * 4;
+                for px in pixels[start..end].chunks_exact_mut(4) {
+                    for (p, c) in px.iter_mut().zip(rgb) {
+                        // src-over on an opaque background: c*a + dst*(1-a)
+                        *p = (c * 255.0 + *p as f32 * (1.0 - opc)).round().min(255.0) as u8;
+                    }
+                }
+            }
+        }
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        let file = File::create(path).map_err(|e| err(&e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| err(&e))?;
+        writer.write_image_data(&self.pixels).map_err(|e| err(&e))
+    }
+}
+
+/// Captures a frame every `every_ticks` scene ticks, as numbered PNG files in
+/// a directory and/or as the frames of an animated GIF of the whole run.
+pub struct Capture {
+    every_ticks: u64,
+    next_tick: u64,
+    frames: Option<PathBuf>,
+    written: u64,
+    gif: Option<(gif::Encoder<BufWriter<File>>, PathBuf)>,
+    // playback time of one GIF frame, in hundredths of a second
+    delay_cs: u16,
+}
+
+impl Capture {
+    pub fn new(every_ticks: u64, frame_delay_ms: u64) -> Capture {
+        Capture {
+            every_ticks,
+            next_tick: 0,
+            frames: None,
+            written: 0,
+            gif: None,
+            delay_cs: (frame_delay_ms / 10).clamp(1, u16::MAX as u64) as u16,
+        }
+    }
+
+    /// Write `frame-000000.png`, `frame-000001.png`, ... into `dir`.
+    pub fn frames_to(mut self, dir: &Path, scene: &Scene) -> Result<Capture, String> {
+        frame_size(scene).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        self.frames = Some(dir.to_path_buf());
+        Ok(self)
+    }
+
+    /// Append every frame to an animated GIF that loops forever.
+    pub fn gif_to(mut self, path: &Path, scene: &Scene) -> Result<Capture, String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        let size = scene.size();
+        let scale = scene.config().world.scale as f64;
+        let (width, height) = (size.x as f64 * scale, size.y as f64 * scale);
+        if width > u16::MAX as f64 || height > u16::MAX as f64 {
+            return Err(err(&"the world is too large for a GIF at this scale"));
+        }
+        frame_size(scene).map_err(|e| err(&e))?;
+        let file = File::create(path).map_err(|e| err(&e))?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+            .map_err(|e| err(&e))?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| err(&e))?;
+        self.gif = Some((encoder, path.to_path_buf()));
+        Ok(self)
+    }
+
+    /// Capture a frame if the scene has reached the next capture tick.
+    pub fn poll(&mut self, scene: &Scene) -> Result<(), String> {
+        let ticks = scene.ticks.load(Ordering::Relaxed);
+        if ticks < self.next_tick {
+            return Ok(());
+        }
+        self.next_tick = ticks - ticks % self.every_ticks + self.every_ticks;
+        let mut frame = Frame::render(scene)?;
+        if let Some(dir) = &self.frames {
+            frame.save_png(&dir.join(format!("frame-{:06}.png", self.written)))?;
+        }
+        if let Some((encoder, path)) = &mut self.gif {
+            let (w, h) = (frame.width as u16, frame.height as u16);
+            let mut gif_frame = gif::Frame::from_rgba_speed(w, h, &mut frame.pixels, 10);
+            gif_frame.delay = self.delay_cs;
+            encoder
+                .write_frame(&gif_frame)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprites_fill_their_cells() {
+        // two 2x2 cells: an opaque red one and a half-transparent white one
+        let sprites = [
+            (1.0, 1.0, 1.0, ([1.0, 0.0, 0.0], 1.0)),
+            (3.0, 1.0, 1.0, ([1.0, 1.0, 1.0], 0.5)),
+        ];
+        let frame = Frame::rasterize(4, 3, &sprites);
+        let px = |x: usize, y: usize| &frame.pixels[(y * 4 + x) * 4..][..4];
+        assert_eq!(px(0, 0), [255, 0, 0, 255]);
+        assert_eq!(px(1, 1), [255, 0, 0, 255]);
+        assert_eq!(px(2, 1), [128, 128, 128, 255]);
+        // the row below every sprite stays background
+        assert_eq!(px(0, 2), [0, 0, 0, 255]);
+        assert_eq!(px(3, 2), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        assert_eq!(frame_bytes(4, 3), Some(48));
+        assert_eq!(frame_bytes(65535 * 255, 65535 * 255), None, "u32 would overflow");
+        let mut config = crate::config::Config::default();
+        (config.world.width, config.world.height, config.world.scale) = (65535, 65535, 255);
+        config.world.storage = crate::grid::Backend::Map;
+        let scene = Scene::new(std::sync::Arc::new(config));
+        let dir = std::env::temp_dir().join("dots-oversized-frames");
+        let err = Capture::new(1, 100).frames_to(&dir, &scene).err().unwrap();
+        assert!(err.contains("too large"), "{}", err);
+        assert!(!dir.exists(), "refused before anything is created");
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::clock::{MAX_SCALE, MIN_SCALE};
+
+pub const USAGE: &str = "\
+usage: dots [--headless (--ticks N | --seconds S)] [--seed N] [--engine async|step|parallel]
+            [--load FILE] [--save FILE] [--config FILE] [--stats FILE] [--species FILE]
+            [--lineage FILE] [--frames DIR] [--gif FILE] [--speed X] [--progress]
+            [--edits FILE] [--replay FILE] [--genome FILE]
+       dots --bench [--config FILE]
+       dots --genes table|json
+       dots decode [GENOME] [--config FILE]
+
+  --headless     run the simulation without a window or OpenGL context
+  --ticks N      headless: stop after N dot ticks
+  --seconds S    headless: stop after S seconds of wall-clock time
+  --progress     headless: print the HUD's figures to stderr at every stats sample
+  --seed N       master seed for every random choice (default: random, reported)
+  --engine E     async: a tokio task per dot (default)
+                 step:  deterministic discrete-event scheduler
+                 parallel: deterministic fixed steps, dots decide in parallel
+  --load FILE    resume from a world snapshot instead of seeding a new world
+  --save FILE    write a world snapshot when the run ends
+  --config FILE  TOML file of world and evolution parameters (see dots.toml)
+  --stats FILE   record population statistics over time (.csv, else JSON Lines)
+  --species FILE record species sizes, emergences and extinctions (JSON Lines)
+  --lineage FILE write the ancestry of the survivors when the run ends
+                 (.graphml for GraphML, else a Newick tree)
+  --frames DIR   write a PNG frame every capture.every_ticks ticks into DIR
+  --gif FILE     encode the same frames as an animated GIF
+  --speed X      run simulation time at X times wall-clock speed, 0.1 to 10
+                 (headless: async engine only; the others run flat out)
+  --edits FILE   record every edit made with the painting tools (JSON Lines)
+  --replay FILE  apply the edits recorded in FILE as simulation time reaches them
+  --genome FILE  window: the genome the inject tool paints with (text format)
+  --bench        time the grid storage backends at several sizes, then exit
+  --genes F      print the genome's gene map (name, offset, width, count and
+                 decoder of every gene) as a table or as JSON, then exit
+  decode         print the traits and brain weights of GENOME, a genome in the
+                 text format (dots-genome:1:...), or of one read from stdin";
+
+/// When a headless run stops: after a number of dot ticks, or a wall-clock span.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Ticks(u64),
+    Duration(Duration),
+}
+
+/// How `--genes` prints the gene map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeneFormat {
+    Table,
+    Json,
+}
+
+impl std::str::FromStr for GeneFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<GeneFormat, ()> {
+        match s {
+            "table" => Ok(GeneFormat::Table),
+            "json" => Ok(GeneFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the world is advanced.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Engine {
+    /// Every dot ticks on its own tokio timer; effects race through the channel.
+    #[default]
+    Async,
+    /// The deterministic discrete-event `Stepper`.
+    Step,
+    /// The deterministic, data-parallel fixed-step engine.
+    Parallel,
+}
+
+impl std::str::FromStr for Engine {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Engine, ()> {
+        match s {
+            "async" => Ok(Engine::Async),
+            "step" => Ok(Engine::Step),
+            "parallel" => Ok(Engine::Parallel),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// `Some` when running headless; the windowed viewer has no limit.
+    pub headless: Option<Limit>,
+    pub seed: Option<u64>,
+    pub engine: Engine,
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub species: Option<PathBuf>,
+    pub lineage: Option<PathBuf>,
+    pub frames: Option<PathBuf>,
+    pub gif: Option<PathBuf>,
+    pub speed: Option<f64>,
+    pub progress: bool,
+    pub edits: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub genome: Option<PathBuf>,
+    pub bench: bool,
+    pub genes: Option<GeneFormat>,
+    /// The genome to decode, or "-" to read it from stdin.
+    pub decode: Option<String>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut headless = false;
+        let mut limit = None;
+        let mut seed = None;
+        let mut engine = Engine::default();
+        let mut load = None;
+        let mut save = None;
+        let mut config = None;
+        let mut stats = None;
+        let mut species = None;
+        let mut lineage = None;
+        let mut frames = None;
+        let mut gif = None;
+        let mut speed = None;
+        let mut progress = false;
+        let mut edits = None;
+        let mut replay = None;
+        let mut genome = None;
+        let mut bench = false;
+        let mut genes = None;
+        let mut decode = None;
+        let mut args = args.into_iter().peekable();
+        if args.next_if(|a| a == "decode").is_some() {
+            decode = Some(args.next_if(|a| !a.starts_with("--")).unwrap_or_else(|| "-".into()));
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--bench" => bench = true,
+                "--progress" => progress = true,
+                "--ticks" => {
+                    let n = value(&arg, args.next())?;
+                    limit = Some(Limit::Ticks(n));
+                }
+                "--seconds" => {
+                    let s: f64 = value(&arg, args.next())?;
+                    if !(s.is_finite() && s > 0.0) {
+                        return Err(format!("--seconds must be positive, got {}", s));
+                    }
+                    limit = Some(Limit::Duration(Duration::from_secs_f64(s)));
+                }
+                "--seed" => seed = Some(value(&arg, args.next())?),
+                "--engine" => engine = value(&arg, args.next())?,
+                "--load" => load = Some(value(&arg, args.next())?),
+                "--save" => save = Some(value(&arg, args.next())?),
+                "--config" => config = Some(value(&arg, args.next())?),
+                "--stats" => stats = Some(value(&arg, args.next())?),
+                "--species" => species = Some(value(&arg, args.next())?),
+                "--lineage" => lineage = Some(value(&arg, args.next())?),
+                "--frames" => frames = Some(value(&arg, args.next())?),
+                "--gif" => gif = Some(value(&arg, args.next())?),
+                "--edits" => edits = Some(value(&arg, args.next())?),
+                "--replay" => replay = Some(value(&arg, args.next())?),
+                "--genome" => genome = Some(value(&arg, args.next())?),
+                "--genes" => genes = Some(value(&arg, args.next())?),
+                "--speed" => {
+                    let x: f64 = value(&arg, args.next())?;
+                    if !(MIN_SCALE..=MAX_SCALE).contains(&x) {
+                        return Err(format!(
+                            "--speed must be between {} and {}, got {}",
+                            MIN_SCALE, MAX_SCALE, x
+                        ));
+                    }
+                    speed = Some(x);
+                }
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+        if headless && genome.is_some() {
+            return Err("--genome only applies to the window's inject tool".to_string());
+        }
+        match (headless, limit) {
+            (true, None) => Err("--headless needs --ticks or --seconds".to_string()),
+            (false, Some(_)) => Err("--ticks/--seconds only apply with --headless".to_string()),
+            (_, headless) => Ok(Args {
+                headless,
+                seed,
+                engine,
+                load,
+                save,
+                config,
+                stats,
+                species,
+                lineage,
+                frames,
+                gif,
+                speed,
+                progress,
+                edits,
+                replay,
+                genome,
+                bench,
+                genes,
+                decode,
+            }),
+        }
+    }
+}
+
+fn value<T: std::str::FromStr>(flag: &str, v: Option<String>) -> Result<T, String> {
+    let v = v.ok_or_else(|| format!("{} needs a value", flag))?;
+    v.parse().map_err(|_| format!("invalid value '{}' for {}", v, flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Args, String> {
+        Args::parse(s.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn windowed_by_default() {
+        assert_eq!(parse(""), Ok(Args::default()));
+    }
+
+    #[test]
+    fn headless_needs_a_limit() {
+        assert_eq!(parse("--headless --ticks 500").unwrap().headless, Some(Limit::Ticks(500)));
+        assert_eq!(
+            parse("--seconds 1.5 --headless").unwrap().headless,
+            Some(Limit::Duration(Duration::from_millis(1500)))
+        );
+        assert!(parse("--headless").is_err());
+        assert!(parse("--ticks 10").is_err());
+        assert!(parse("--headless --seconds -1").is_err());
+        assert!(parse("--headless --ticks many").is_err());
+        assert!(parse("--bogus").is_err());
+    }
+
+    #[test]
+    fn seed_and_engine() {
+        let args = parse("--seed 42 --engine step").unwrap();
+        assert_eq!(args.seed, Some(42));
+        assert_eq!(args.engine, Engine::Step);
+        assert_eq!(parse("--engine parallel").unwrap().engine, Engine::Parallel);
+        assert_eq!(parse("").unwrap().engine, Engine::Async);
+        assert!(parse("--engine warp").is_err());
+        assert!(parse("--seed").is_err());
+    }
+
+    #[test]
+    fn bench_is_a_flag() {
+        assert!(parse("--bench").unwrap().bench);
+        assert!(!parse("").unwrap().bench);
+    }
+
+    #[test]
+    fn speed_must_be_in_the_clock_range() {
+        assert_eq!(parse("--speed 2.5").unwrap().speed, Some(2.5));
+        assert!(parse("--speed 20").is_err());
+        assert!(parse("--speed fast").is_err());
+    }
+
+    #[test]
+    fn progress_is_a_flag() {
+        assert!(parse("--headless --ticks 5 --progress").unwrap().progress);
+        assert!(!parse("--headless --ticks 5").unwrap().progress);
+    }
+
+    #[test]
+    fn genes_takes_a_format() {
+        assert_eq!(parse("--genes table").unwrap().genes, Some(GeneFormat::Table));
+        assert_eq!(parse("--genes json").unwrap().genes, Some(GeneFormat::Json));
+        assert!(parse("--genes xml").is_err());
+        assert!(parse("--genes").is_err());
+    }
+
+    #[test]
+    fn decode_reads_the_genome_or_stdin() {
+        let args = parse("decode dots-genome:1:ab").unwrap();
+        assert_eq!(args.decode.as_deref(), Some("dots-genome:1:ab"));
+        assert_eq!(parse("decode --config c.toml").unwrap().decode.as_deref(), Some("-"));
+        assert!(parse("--seed 1 decode").is_err(), "decode comes first");
+    }
+
+    #[test]
+    fn file_paths() {
+        let args =
+            parse("--load in.json --save out.json --config dots.toml --stats s.csv").unwrap();
+        assert_eq!(args.stats, Some(PathBuf::from("s.csv")));
+        assert_eq!(parse("--lineage t.nwk").unwrap().lineage, Some(PathBuf::from("t.nwk")));
+        assert_eq!(parse("--species s.jsonl").unwrap().species, Some(PathBuf::from("s.jsonl")));
+        let capture = parse("--frames out --gif run.gif").unwrap();
+        assert_eq!(capture.frames, Some(PathBuf::from("out")));
+        assert_eq!(capture.gif, Some(PathBuf::from("run.gif")));
+        let edits = parse("--edits e.jsonl --replay r.jsonl --genome g.txt").unwrap();
+        assert_eq!(edits.edits, Some(PathBuf::from("e.jsonl")));
+        assert_eq!(edits.replay, Some(PathBuf::from("r.jsonl")));
+        assert_eq!(edits.genome, Some(PathBuf::from("g.txt")));
+        assert!(parse("--headless --ticks 5 --genome g.txt").is_err());
+        assert_eq!(args.load, Some(PathBuf::from("in.json")));
+        assert_eq!(args.config, Some(PathBuf::from("dots.toml")));
+        assert_eq!(args.save, Some(PathBuf::from("out.json")));
+        assert!(parse("--save").is_err());
+    }
+}
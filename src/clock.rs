@@ -0,0 +1,166 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// The slowest and fastest the simulation can run, relative to wall-clock time.
+pub const MIN_SCALE: f64 = 0.1;
+pub const MAX_SCALE: f64 = 10.0;
+
+struct State {
+    // virtual time at `origin`, which moves on whenever the clock changes
+    at: Duration,
+    origin: Instant,
+    scale: f64,
+    paused: bool,
+}
+
+impl State {
+    fn now(&self) -> Duration {
+        if self.paused {
+            self.at
+        } else {
+            self.at + self.origin.elapsed().mul_f64(self.scale)
+        }
+    }
+}
+
+/// The simulation's own time, which the async engine's tickers wait on instead
+/// of the wall clock. It runs at `scale` times wall-clock speed, stands still
+/// while paused and can be stepped forward by hand while it does.
+///
+/// Propagators don't wait on it: effects already sent still land while the
+/// clock is paused, so a paused world settles instead of freezing mid-exchange.
+/// The synchronous engines keep their own virtual time; for them the clock
+/// only says whether, and how fast, to advance them.
+pub struct Clock {
+    state: Mutex<State>,
+    // wakes sleepers when the clock is paused, resumed, stepped or rescaled
+    changed: watch::Sender<()>,
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock {
+            state: Mutex::new(State {
+                at: Duration::ZERO,
+                origin: Instant::now(),
+                scale: 1.0,
+                paused: false,
+            }),
+            changed: watch::channel(()).0,
+        }
+    }
+}
+
+impl Clock {
+    /// Virtual time since the clock started.
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.state.lock().unwrap().scale
+    }
+
+    pub fn pause(&self) {
+        self.update(|s| s.paused = true);
+    }
+
+    pub fn resume(&self) {
+        self.update(|s| s.paused = false);
+    }
+
+    /// Set the speed, clamped to `MIN_SCALE..=MAX_SCALE`; returns what was set.
+    pub fn set_scale(&self, scale: f64) -> f64 {
+        let scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+        self.update(|s| s.scale = scale);
+        scale
+    }
+
+    /// Jump to virtual time `at`, e.g. to carry on a loaded snapshot's time.
+    pub fn set(&self, at: Duration) {
+        self.update(|s| s.at = at);
+    }
+
+    /// Move a paused clock forward by `by`, waking whatever falls due. Does
+    /// nothing while the clock runs.
+    pub fn step(&self, by: Duration) {
+        self.update(|s| {
+            if s.paused {
+                s.at += by;
+            }
+        });
+    }
+
+    /// Wait until virtual time reaches `deadline`, however the clock is
+    /// paused, stepped or rescaled in the meantime.
+    pub async fn sleep_until(&self, deadline: Duration) {
+        // subscribe first, so a change made after the check still wakes us
+        let mut changed = self.changed.subscribe();
+        loop {
+            let wait = {
+                let state = self.state.lock().unwrap();
+                let now = state.now();
+                if now >= deadline {
+                    return;
+                }
+                (!state.paused).then(|| (deadline - now).div_f64(state.scale))
+            };
+            match wait {
+                Some(wait) => {
+                    tokio::select! {
+                        () = tokio::time::sleep(wait) => {}
+                        _ = changed.changed() => {}
+                    }
+                }
+                // the clock holds the sender, so this can't fail
+                None => changed.changed().await.expect("the clock holds the sender"),
+            }
+        }
+    }
+
+    // Apply `f` from the current instant on and wake every sleeper.
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = self.state.lock().unwrap();
+        state.at = state.now();
+        state.origin = Instant::now();
+        f(&mut state);
+        drop(state);
+        self.changed.send_replace(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn pause_step_and_resume() {
+        let clock = Arc::new(Clock::default());
+        clock.pause();
+        let frozen = clock.now();
+        let sleeper = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep_until(frozen + Duration::from_millis(30)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(clock.now(), frozen, "a paused clock stands still");
+        assert!(!sleeper.is_finished());
+
+        clock.step(Duration::from_millis(20));
+        assert_eq!(clock.now(), frozen + Duration::from_millis(20));
+        clock.step(Duration::from_millis(20));
+        sleeper.await.unwrap();
+
+        assert_eq!(clock.set_scale(50.0), MAX_SCALE);
+        clock.resume();
+        clock.sleep_until(clock.now() + Duration::from_millis(500)).await;
+        clock.step(Duration::from_secs(60));
+        assert!(clock.now() < frozen + Duration::from_secs(60), "stepping needs a paused clock");
+    }
+}
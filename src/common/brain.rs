@@ -1,13 +1,11 @@
-use bitvec::prelude::*;
-
-use crate::common::dna::{SIZE, TRAIT_BITS};
+use crate::common::dna::SIZE;
+use crate::common::genome::{self, W1, W2};
 
 // Net shape. Inputs: 8 neighbours × (r, g, b) + own energy + one constant bias
 // unit. Outputs: 8 DIGEST directions + 8 SEED directions + 1 IDLE.
 pub const N_IN: usize = 8 * 3 + 2;
 pub const N_HID: usize = 8;
 pub const N_OUT: usize = 8 + 8 + 1;
-const W_BITS: usize = 4;
 
 /// A dot's brain: a small MLP whose weights are decoded from its DNA. It maps
 /// the raw senses of the 8 surrounding cells to a score for every
@@ -18,33 +16,31 @@ pub struct Brain {
     w2: [[f32; N_HID]; N_OUT],
 }
 
-// Read W_BITS from the genome and map [0, 2^W_BITS) → [-1, 1). The DNA
-// Sequencer only yields unsigned [0,1), so weights need their own signed map.
-fn read_weight(bits: &BitSlice<u64, Lsb0>, cursor: &mut usize) -> f32 {
-    let v: u32 = bits[*cursor..*cursor + W_BITS].load::<u32>();
-    *cursor += W_BITS;
-    (v as f32 / (1u32 << W_BITS) as f32) * 2.0 - 1.0
-}
-
 impl Brain {
+    /// Decode the weights from the genome's `w1` and `w2` genes, row by row.
     pub fn from_seq(seq: &[u64; SIZE]) -> Brain {
-        let bits = seq.view_bits::<Lsb0>();
-        let mut cursor = TRAIT_BITS;
+        let weight = |gene: genome::Gene, i| genome::signed(gene.read(seq, i), gene.width);
         let mut w1 = [[0.0f32; N_IN]; N_HID];
-        for row in w1.iter_mut() {
-            for w in row.iter_mut() {
-                *w = read_weight(bits, &mut cursor);
-            }
+        for (i, w) in w1.iter_mut().flatten().enumerate() {
+            *w = weight(W1, i);
         }
         let mut w2 = [[0.0f32; N_HID]; N_OUT];
-        for row in w2.iter_mut() {
-            for w in row.iter_mut() {
-                *w = read_weight(bits, &mut cursor);
-            }
+        for (i, w) in w2.iter_mut().flatten().enumerate() {
+            *w = weight(W2, i);
         }
         Brain { w1, w2 }
     }
 
+    /// Input-to-hidden weights: a row per hidden unit, a column per input.
+    pub fn w1(&self) -> &[[f32; N_IN]; N_HID] {
+        &self.w1
+    }
+
+    /// Hidden-to-output weights: a row per output, a column per hidden unit.
+    pub fn w2(&self) -> &[[f32; N_HID]; N_OUT] {
+        &self.w2
+    }
+
     pub fn forward(&self, input: &[f32; N_IN]) -> [f32; N_OUT] {
         let mut hidden = [0.0f32; N_HID];
         for (h, row) in self.w1.iter().enumerate() {
@@ -1,20 +1,58 @@
-use std::hash::{Hash,Hasher};
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug)]
+/// A cell on the grid. Signed, so a step off the west or north edge is a real
+/// off-grid coordinate rather than aliasing onto column or row 0; only the
+/// renderer converts to `f64`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Coord {
-    pub x: f64,
-    pub y: f64
+    pub x: i32,
+    pub y: i32,
 }
 
-impl PartialEq for Coord {
-    fn eq(&self, other: &Self) -> bool { self.x as u32 == other.x as u32 && self.y as u32 == other.y as u32 }
+impl Coord {
+    pub fn new(x: i32, y: i32) -> Coord {
+        Coord { x, y }
+    }
+
+    /// The coordinate `dx`, `dy` away, or None if that overflows.
+    pub fn offset(self, dx: i32, dy: i32) -> Option<Coord> {
+        Some(Coord {
+            x: self.x.checked_add(dx)?,
+            y: self.y.checked_add(dy)?,
+        })
+    }
+
+    /// Row-major index of this cell on a grid of `size` cells, or None if it
+    /// is off the grid.
+    pub fn index(self, size: Coord) -> Option<usize> {
+        if !(0..size.x).contains(&self.x) || !(0..size.y).contains(&self.y) {
+            return None;
+        }
+        Some(self.y as usize * size.x as usize + self.x as usize)
+    }
+
+    /// The cell at row-major `index` on a grid of `size` cells.
+    pub fn from_index(index: usize, size: Coord) -> Coord {
+        let w = size.x as usize;
+        Coord::new((index % w) as i32, (index / w) as i32)
+    }
 }
 
-impl Eq for Coord {}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Hash for Coord {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        (self.x as u32).hash(state);
-        (self.y as u32).hash(state);
+    #[test]
+    fn index_round_trips_and_rejects_off_grid() {
+        let size = Coord::new(4, 3);
+        for i in 0..12 {
+            assert_eq!(Coord::from_index(i, size).index(size), Some(i));
+        }
+        assert_eq!(Coord::new(3, 0).index(size), Some(3));
+        assert_eq!(Coord::new(0, 1).index(size), Some(4));
+        assert_eq!(Coord::new(-1, 0).index(size), None);
+        assert_eq!(Coord::new(4, 0).index(size), None);
+        assert_eq!(Coord::new(i32::MAX, 0).offset(1, 0), None);
+        assert_eq!(Coord::new(0, 0).offset(-1, 1), Some(Coord::new(-1, 1)));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,123 @@
+use bitvec::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::common::dna::SIZE;
+use crate::common::genome::{BITS, GENES};
+
+/// How two parents' genomes recombine: a crossover picks, bit by bit, which
+/// parent each bit of the child comes from.
+pub trait Crossover: Send + Sync {
+    /// Set where the child takes the first parent's bit, clear where it takes
+    /// the second's.
+    fn mask(&self, rng: &mut dyn RngCore) -> [u64; SIZE];
+}
+
+/// Which `Crossover` reproduction uses, from `genetics.crossover`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    #[default]
+    Uniform,
+    SinglePoint,
+    TwoPoint,
+    GeneAware,
+}
+
+impl Method {
+    pub fn crossover(self) -> &'static dyn Crossover {
+        match self {
+            Method::Uniform => &Uniform,
+            Method::SinglePoint => &SinglePoint,
+            Method::TwoPoint => &TwoPoint,
+            Method::GeneAware => &GeneAware,
+        }
+    }
+}
+
+/// Every bit from either parent with even odds.
+pub struct Uniform;
+
+impl Crossover for Uniform {
+    fn mask(&self, rng: &mut dyn RngCore) -> [u64; SIZE] {
+        rng.gen()
+    }
+}
+
+/// The first parent's genes up to a random cut, the second's after it.
+pub struct SinglePoint;
+
+impl Crossover for SinglePoint {
+    fn mask(&self, rng: &mut dyn RngCore) -> [u64; SIZE] {
+        let mut mask = [0; SIZE];
+        mask.view_bits_mut::<Lsb0>()[..rng.gen_range(1..BITS)].fill(true);
+        mask
+    }
+}
+
+/// The second parent's genes between two random cuts, the first's outside.
+pub struct TwoPoint;
+
+impl Crossover for TwoPoint {
+    fn mask(&self, rng: &mut dyn RngCore) -> [u64; SIZE] {
+        let (a, b) = (rng.gen_range(1..BITS), rng.gen_range(1..BITS));
+        let mut mask = [u64::MAX; SIZE];
+        mask.view_bits_mut::<Lsb0>()[a.min(b)..a.max(b)].fill(false);
+        mask
+    }
+}
+
+/// Every value of every gene (a colour channel, a brain weight) whole from
+/// either parent with even odds, so none is ever split between them.
+pub struct GeneAware;
+
+impl Crossover for GeneAware {
+    fn mask(&self, rng: &mut dyn RngCore) -> [u64; SIZE] {
+        let mut mask = [0; SIZE];
+        let bits = mask.view_bits_mut::<Lsb0>();
+        for gene in GENES {
+            for i in 0..gene.count {
+                if rng.gen() {
+                    let start = gene.offset + i * gene.width;
+                    bits[start..start + gene.width].fill(true);
+                }
+            }
+        }
+        mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::genome::W1;
+
+    #[test]
+    fn masks_keep_their_shape() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..50 {
+            let ones = |m: [u64; SIZE]| m.view_bits::<Lsb0>()[..BITS].to_bitvec();
+
+            // one run of ones, then zeros
+            let single = ones(SinglePoint.mask(&mut rng));
+            let cut = single.first_zero().unwrap();
+            assert!(cut > 0 && single[cut..].not_any());
+
+            // ones, a run of zeros, ones
+            let two = ones(TwoPoint.mask(&mut rng));
+            if let Some(a) = two.first_zero() {
+                let b = a + two[a..].first_one().unwrap_or(BITS - a);
+                assert!(two[b..].all());
+            }
+
+            // every weight comes whole from one parent
+            let gene = ones(GeneAware.mask(&mut rng));
+            for i in 0..W1.count {
+                let w = &gene[W1.offset + i * W1.width..][..W1.width];
+                assert!(w.all() || w.not_any());
+            }
+        }
+        let a = Method::Uniform.crossover().mask(&mut StdRng::seed_from_u64(1));
+        assert_eq!(a, StdRng::seed_from_u64(1).gen::<[u64; SIZE]>(), "uniform is a random mask");
+    }
+}
@@ -28,11 +28,25 @@ impl Direction {
         }
     }
 
+    /// Unit grid step (dx, dy) in this direction; north is -y.
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::NORTH => (0, -1),
+            Direction::NORTHEAST => (1, -1),
+            Direction::EAST => (1, 0),
+            Direction::SOUTHEAST => (1, 1),
+            Direction::SOUTH => (0, 1),
+            Direction::SOUTHWEST => (-1, 1),
+            Direction::WEST => (-1, 0),
+            Direction::NORTHWEST => (-1, -1),
+        }
+    }
+
     pub fn opposite(&self) -> Direction {
-        if *self == Direction::NORTH { return Direction::SOUTH; }
-        else if *self == Direction::SOUTH { return Direction::NORTH; }
-        else if *self == Direction::WEST { return Direction::EAST; }
-        else { return Direction::WEST; }
+        if *self == Direction::NORTH { Direction::SOUTH }
+        else if *self == Direction::SOUTH { Direction::NORTH }
+        else if *self == Direction::WEST { Direction::EAST }
+        else { Direction::WEST }
     }
 }
 
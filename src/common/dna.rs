@@ -1,26 +1,38 @@
-use bitvec::prelude::*;
 use rand::prelude::*;
+
+use crate::common::mutation;
+use crate::common::genome::{self, COLOR, DIGEST_MASK, MUTATION_RATE, REACTION_TIME, SEED_INVEST};
+use crate::config::Genetics;
+use crate::lineage::Lineage;
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
 use std::time::Duration;
 
-pub const SIZE: usize = 23;
-
-/// Bits of `seq` consumed by `Dna::new` for the heritable traits — colour
-/// (3×6), digest_mask (3×8), reaction_time (8), seed_invest (8). The neural-net
-/// weights occupy the bits after this — see `common::brain`, which decodes them
-/// from the same `seq`.
-pub const TRAIT_BITS: usize = 58;
+/// Words in a genome: enough for every gene in the gene map (`genome::GENES`),
+/// which `Dna::new` and `Brain::from_seq` both decode through.
+pub const SIZE: usize = genome::BITS.div_ceil(64);
 
-/// Floor on a dot's reaction_time so metabolism can't evolve down to a 0ms
-/// busy loop. The 8-bit gene adds 0..256ms on top, giving a [16, 271]ms range.
+/// Default floor on a dot's reaction_time so metabolism can't evolve down to a
+/// 0ms busy loop. The 8-bit gene adds 0..256ms on top, giving a [16, 271]ms
+/// range. Configurable as `genetics.reaction_floor_ms`.
 pub const REACTION_FLOOR_MS: u64 = 16;
 
-/// Maximum energy a dot will invest in one seed; the 8-bit gene scales [0, this].
-/// The cost is conserved (it becomes the offspring's starting energy), so this
-/// only bounds the evolvable range — like every other trait's decode.
+/// Default maximum energy a dot will invest in one seed; the 8-bit gene scales
+/// [0, this]. The cost is conserved (it becomes the offspring's starting
+/// energy), so this only bounds the evolvable range — like every other trait's
+/// decode. Configurable as `genetics.seed_invest_max`.
 pub const SEED_INVEST_MAX: f32 = 0.25;
 
+/// Default maximum of a dot's own mutation rate: the chance each bit of its
+/// offspring flips, on top of the configured rates. The 8-bit gene scales
+/// [0, this], so at the top a genome takes about three flips.
+/// Configurable as `genetics.mutation_rate_max`.
+pub const MUTATION_RATE_MAX: f32 = 0.002;
+
+// The genome text format's header and version (see `Dna::format`); the
+// version goes up whenever the layout of the text or of `seq` changes.
+const TEXT_HEADER: &str = "dots-genome";
+const TEXT_VERSION: u32 = 1;
+
 #[derive(Clone, Copy, Debug)]
 pub struct Dna {
     pub seq: [u64; SIZE],
@@ -31,32 +43,93 @@ pub struct Dna {
     pub reaction_time: Duration,
     /// Energy this dot invests into each seed (transferred to the offspring).
     pub seed_invest: f32,
+    /// The chance each bit of this genome's offspring flips (see `combine`),
+    /// so how fast a lineage explores can itself evolve.
+    pub mutation_rate: f32,
     /// Identity: a colour derived from the *whole* genome so genetically similar
     /// dots look alike on screen. Shown to the viewer; not sensed by other dots.
     pub display_color: [f32; 3],
+    /// Ancestry bookkeeping, assigned when the genome enters the scene — not
+    /// decoded from `seq`, so two genomes with equal `seq` are equal.
+    pub lineage: Lineage,
 }
 
 impl Dna {
-    pub fn new(seq: [u64; SIZE]) -> Dna {
-        let mut s = Sequencer {
-            seq: Rc::from(seq),
-            cursor: 0,
-        };
-        return Dna {
+    pub fn new(seq: [u64; SIZE], genetics: &Genetics) -> Dna {
+        let decode = |gene: genome::Gene, i| gene.decode(&seq, i, genetics);
+        Dna {
             seq,
-            color: [
-                (s.u(6) + 64) as f32 / u8::MAX as f32,
-                (s.u(6) + 64) as f32 / u8::MAX as f32,
-                (s.u(6) + 64) as f32 / u8::MAX as f32,
-            ],
-            digest_mask: [s.f(8), s.f(8), s.f(8)], //[0.0, 0.0, 0.0],
-            reaction_time: Duration::from_millis(REACTION_FLOOR_MS + s.u(8) as u64),
-            seed_invest: s.f(8) * SEED_INVEST_MAX,
+            color: [0, 1, 2].map(|i| decode(COLOR, i)),
+            digest_mask: [0, 1, 2].map(|i| decode(DIGEST_MASK, i)),
+            reaction_time: Duration::from_millis(decode(REACTION_TIME, 0) as u64),
+            seed_invest: decode(SEED_INVEST, 0),
+            mutation_rate: decode(MUTATION_RATE, 0),
             display_color: genome_color(&seq),
+            lineage: Lineage::default(),
+        }
+    }
+}
+
+impl Dna {
+    /// The genome as one line of text to paste and keep:
+    /// `dots-genome:1:` then every word of `seq` as 16 hex digits, first word
+    /// first, then `:` and a checksum of the words as 8 hex digits. The
+    /// lineage isn't kept; a parsed genome is a founder.
+    pub fn format(&self) -> String {
+        let hex: String = self.seq.iter().map(|w| format!("{:016x}", w)).collect();
+        format!("{}:{}:{}:{:08x}", TEXT_HEADER, TEXT_VERSION, hex, checksum(&self.seq))
+    }
+
+    /// Read a genome written by `format`. Whitespace anywhere is ignored, so
+    /// text wrapped by a chat client or an editor still parses.
+    pub fn parse(text: &str, genetics: &Genetics) -> Result<Dna, String> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let parts: Vec<&str> = text.split(':').collect();
+        let not_a_genome = format!("not a genome: expected '{}:{}:...'", TEXT_HEADER, TEXT_VERSION);
+        let [header, version, hex, sum] = parts[..] else {
+            return Err(not_a_genome);
         };
+        if header != TEXT_HEADER {
+            return Err(not_a_genome);
+        }
+        if version != TEXT_VERSION.to_string() {
+            return Err(format!("genome format version {} (expected {})", version, TEXT_VERSION));
+        }
+        // `from_str_radix` alone would take a sign, so check digit by digit
+        let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+        if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex digit '{}'", c));
+        }
+        if hex.len() != SIZE * 16 {
+            return Err(format!("expected {} hex digits, found {}", SIZE * 16, hex.len()));
+        }
+        if sum.len() != 8 || !is_hex(sum) {
+            return Err(format!("invalid checksum '{}'", sum));
+        }
+        let mut seq = [0; SIZE];
+        for (i, word) in seq.iter_mut().enumerate() {
+            *word = u64::from_str_radix(&hex[i * 16..(i + 1) * 16], 16).unwrap();
+        }
+        if u32::from_str_radix(sum, 16).unwrap() != checksum(&seq) {
+            return Err("checksum mismatch: the genome was altered or cut short".to_string());
+        }
+        Ok(Dna::new(seq, genetics))
     }
 }
 
+// FNV-1a over the words' bytes, least significant first.
+fn checksum(seq: &[u64; SIZE]) -> u32 {
+    seq.iter().flat_map(|w| w.to_le_bytes()).fold(0x811c_9dc5, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Bits that differ between two genomes — the measure of relatedness used
+/// for display colours and species.
+pub fn hamming(a: &[u64; SIZE], b: &[u64; SIZE]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
 // A locality-preserving projection of the whole genome to RGB: each channel is
 // the Hamming distance from the genome to a fixed pseudo-random anchor, squashed
 // through a sigmoid. Small genetic change -> small Hamming change -> small colour
@@ -99,38 +172,19 @@ impl Hash for Dna {
     }
 }
 
-pub fn combine(mine: Dna, other: Dna) -> Dna {
-    let mut rng = thread_rng();
-    let mask: [u64; SIZE] = rng.gen();
+/// A child of `mine` and `other`: their genomes recombined by the configured
+/// crossover (`genetics.crossover`), then mutated (see `mutation::mutate`) at
+/// the parents' mean mutation rate so the gene pool can innovate new
+/// weights/colours/diets, not just reshuffle the parents' alleles.
+pub fn combine<R: Rng>(mine: Dna, other: Dna, genetics: &Genetics, rng: &mut R) -> Dna {
+    let mask = genetics.crossover.crossover().mask(rng);
     let mut seq: [u64; SIZE] = [0; SIZE];
     for (i, it) in mask.into_iter().enumerate() {
         seq[i] = (it & mine.seq[i]) | (!it & other.seq[i]);
     }
-    // mutation: flip a few random bits so the gene pool can innovate new
-    // weights/colours/diets, not just reshuffle the parents' alleles.
-    let bits = SIZE * 64;
-    for _ in 0..rng.gen_range(0..=3) {
-        let b = rng.gen_range(0..bits);
-        seq[b / 64] ^= 1u64 << (b % 64);
-    }
-    Dna::new(seq)
-}
-
-struct Sequencer {
-    cursor: usize,
-    seq: Rc<[u64; SIZE]>,
-}
-
-impl Sequencer {
-    fn f(&mut self, n: usize) -> f32 {
-        return f32::try_from(i16::try_from(self.u(n)).unwrap()).unwrap()
-            / f32::try_from(2i16.pow(u32::try_from(n).unwrap())).unwrap();
-    }
-
-    fn u(&mut self, n: usize) -> u32 {
-        self.cursor += n;
-        return self.seq.view_bits::<Lsb0>()[self.cursor - n..self.cursor].load::<u32>();
-    }
+    let rate = (mine.mutation_rate + other.mutation_rate) as f64 / 2.0;
+    mutation::mutate(&mut seq, genetics, rate, rng);
+    Dna::new(seq, genetics)
 }
 
 #[cfg(test)]
@@ -139,18 +193,83 @@ mod tests {
 
     #[test]
     fn reaction_time_is_inherited_and_floored() {
+        let g = Genetics::default();
         let seq = [0xabcd_ef01_2345_6789_u64; SIZE];
         // derived from the genome, not random: same seq -> same metabolism.
-        assert_eq!(Dna::new(seq).reaction_time, Dna::new(seq).reaction_time);
-        let ms = Dna::new(seq).reaction_time.as_millis() as u64;
-        assert!(ms >= REACTION_FLOOR_MS && ms <= REACTION_FLOOR_MS + 255);
+        assert_eq!(Dna::new(seq, &g).reaction_time, Dna::new(seq, &g).reaction_time);
+        let ms = Dna::new(seq, &g).reaction_time.as_millis() as u64;
+        assert!((REACTION_FLOOR_MS..=REACTION_FLOOR_MS + 255).contains(&ms));
+    }
+
+    #[test]
+    fn text_round_trips_and_catches_damage() {
+        let g = Genetics::default();
+        let dna = Dna::new([0x0123_4567_89ab_cdef_u64; SIZE], &g);
+        let text = dna.format();
+        assert!(text.starts_with("dots-genome:1:0123456789abcdef"));
+        assert_eq!(Dna::parse(&text, &g).unwrap(), dna);
+        let mut wrapped = text.clone();
+        for i in (60..text.len()).step_by(60).rev() {
+            wrapped.insert_str(i, "\n  ");
+        }
+        assert_eq!(Dna::parse(&wrapped, &g).unwrap(), dna, "whitespace is ignored");
+
+        let typo = text.replacen("0123", "0124", 1);
+        assert!(Dna::parse(&typo, &g).unwrap_err().contains("checksum"));
+        assert!(Dna::parse(&text[..text.len() - 20], &g).is_err());
+        assert!(Dna::parse(&text.replace(":1:", ":2:"), &g).unwrap_err().contains("version"));
+        assert!(Dna::parse("hello", &g).is_err());
+        // a sign would slip through `from_str_radix`
+        let signed = text.replacen("0123", "+123", 1);
+        assert!(Dna::parse(&signed, &g).unwrap_err().contains("invalid hex digit '+'"));
+        let (body, sum) = text.rsplit_once(':').unwrap();
+        let signed = format!("{}:+{}", body, &sum[1..]);
+        assert!(Dna::parse(&signed, &g).unwrap_err().contains("invalid checksum"));
     }
 
     #[test]
     fn seed_invest_is_inherited_and_bounded() {
+        let g = Genetics::default();
         let seq = [0x0f1e_2d3c_4b5a_6978_u64; SIZE];
-        assert_eq!(Dna::new(seq).seed_invest, Dna::new(seq).seed_invest);
-        let inv = Dna::new(seq).seed_invest;
-        assert!(inv >= 0.0 && inv <= SEED_INVEST_MAX);
+        assert_eq!(Dna::new(seq, &g).seed_invest, Dna::new(seq, &g).seed_invest);
+        let inv = Dna::new(seq, &g).seed_invest;
+        assert!((0.0..=SEED_INVEST_MAX).contains(&inv));
+    }
+
+    #[test]
+    fn mutation_rate_is_inherited_and_bounded() {
+        let g = Genetics::default();
+        let seq = [0x5a5a_0ff0_c3c3_9669_u64; SIZE];
+        assert_eq!(Dna::new(seq, &g).mutation_rate, Dna::new(seq, &g).mutation_rate);
+        for raw in [0, 0x80, 0xff] {
+            let mut seq = seq;
+            MUTATION_RATE.write(&mut seq, 0, raw);
+            let rate = Dna::new(seq, &g).mutation_rate;
+            assert!((0.0..=g.mutation_rate_max).contains(&rate), "{} out of range", rate);
+        }
+    }
+
+    #[test]
+    fn offspring_mutate_at_their_parents_rate() {
+        // no flips but the heritable ones, and a rate high enough to count
+        let g = Genetics {
+            max_mutations: 0,
+            mutation_rate_max: 0.05,
+            ..Genetics::default()
+        };
+        let mut rng = StdRng::seed_from_u64(11);
+        let base: [u64; SIZE] = rng.gen();
+        let parent = |raw| {
+            let mut seq = base;
+            MUTATION_RATE.write(&mut seq, 0, raw);
+            Dna::new(seq, &g)
+        };
+        // identical parents, so every differing bit is a mutation
+        let mut changed = |p: Dna| {
+            (0..20).map(|_| hamming(&combine(p, p, &g, &mut rng).seq, &p.seq)).sum::<u32>()
+        };
+        let (still, wild) = (changed(parent(0)), changed(parent(0xff)));
+        assert_eq!(still, 0, "a zero rate never mutates");
+        assert!(wild > 20 * 30, "a 5% rate flips about 70 bits a child, not {}", wild / 20);
     }
 }
@@ -0,0 +1,180 @@
+use bitvec::prelude::*;
+use serde::Serialize;
+
+use crate::common::brain::{N_HID, N_IN, N_OUT};
+use crate::common::dna::SIZE;
+use crate::config::Genetics;
+
+/// How a gene's raw bits become a trait value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decoder {
+    /// A colour channel kept clear of black: `64 + raw` out of 255.
+    Channel,
+    /// `[0, 1)`.
+    Unit,
+    /// Milliseconds: `genetics.reaction_floor_ms + raw`.
+    ReactionMs,
+    /// `[0, genetics.seed_invest_max)`.
+    SeedInvest,
+    /// `[0, genetics.mutation_rate_max)`.
+    MutationRate,
+    /// A brain weight in `[-1, 1)`.
+    Weight,
+}
+
+impl Decoder {
+    pub fn name(self) -> &'static str {
+        match self {
+            Decoder::Channel => "channel",
+            Decoder::Unit => "unit",
+            Decoder::ReactionMs => "reaction_ms",
+            Decoder::SeedInvest => "seed_invest",
+            Decoder::MutationRate => "mutation_rate",
+            Decoder::Weight => "weight",
+        }
+    }
+
+    pub fn decode(self, raw: u32, width: usize, genetics: &Genetics) -> f32 {
+        let unit = raw as f32 / (1u32 << width) as f32;
+        match self {
+            Decoder::Channel => (raw + 64) as f32 / u8::MAX as f32,
+            Decoder::Unit => unit,
+            Decoder::ReactionMs => (genetics.reaction_floor_ms + raw as u64) as f32,
+            Decoder::SeedInvest => unit * genetics.seed_invest_max,
+            Decoder::MutationRate => unit * genetics.mutation_rate_max,
+            Decoder::Weight => signed(raw, width),
+        }
+    }
+}
+
+/// A brain weight from its raw bits: `[0, 2^width)` mapped onto `[-1, 1)`.
+pub fn signed(raw: u32, width: usize) -> f32 {
+    (raw as f32 / (1u32 << width) as f32) * 2.0 - 1.0
+}
+
+/// A run of `count` values of `width` bits each, starting `offset` bits into
+/// the genome (counting from bit 0 of `seq[0]`, least significant first).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Gene {
+    pub name: &'static str,
+    pub offset: usize,
+    pub width: usize,
+    pub count: usize,
+    pub decoder: Decoder,
+}
+
+impl Gene {
+    const fn first(name: &'static str, count: usize, width: usize, decoder: Decoder) -> Gene {
+        Gene {
+            name,
+            offset: 0,
+            width,
+            count,
+            decoder,
+        }
+    }
+
+    // The gene laid out straight after this one.
+    const fn then(self, name: &'static str, count: usize, width: usize, decoder: Decoder) -> Gene {
+        Gene {
+            offset: self.end(),
+            ..Gene::first(name, count, width, decoder)
+        }
+    }
+
+    pub const fn bits(&self) -> usize {
+        self.width * self.count
+    }
+
+    /// The bit after the gene's last.
+    pub const fn end(&self) -> usize {
+        self.offset + self.bits()
+    }
+
+    /// The raw bits of value `i`.
+    pub fn read(&self, seq: &[u64; SIZE], i: usize) -> u32 {
+        let start = self.offset + i * self.width;
+        seq.view_bits::<Lsb0>()[start..start + self.width].load::<u32>()
+    }
+
+    /// Overwrite the raw bits of value `i` with the low `width` bits of `raw`.
+    pub fn write(&self, seq: &mut [u64; SIZE], i: usize, raw: u32) {
+        let start = self.offset + i * self.width;
+        seq.view_bits_mut::<Lsb0>()[start..start + self.width].store::<u32>(raw);
+    }
+
+    /// Value `i`, decoded.
+    pub fn decode(&self, seq: &[u64; SIZE], i: usize, genetics: &Genetics) -> f32 {
+        self.decoder.decode(self.read(seq, i), self.width, genetics)
+    }
+}
+
+// The gene map. Each gene starts where the one before it ends, so a new gene
+// goes at the end: added anywhere else it would move every gene after it, and
+// saved genomes would decode differently.
+pub const COLOR: Gene = Gene::first("color", 3, 6, Decoder::Channel);
+pub const DIGEST_MASK: Gene = COLOR.then("digest_mask", 3, 8, Decoder::Unit);
+pub const REACTION_TIME: Gene = DIGEST_MASK.then("reaction_time", 1, 8, Decoder::ReactionMs);
+pub const SEED_INVEST: Gene = REACTION_TIME.then("seed_invest", 1, 8, Decoder::SeedInvest);
+/// The brain's input-to-hidden weights, row by row (see `Brain`).
+pub const W1: Gene = SEED_INVEST.then("w1", N_HID * N_IN, 4, Decoder::Weight);
+/// The brain's hidden-to-output weights, row by row.
+pub const W2: Gene = W1.then("w2", N_OUT * N_HID, 4, Decoder::Weight);
+pub const MUTATION_RATE: Gene = W2.then("mutation_rate", 1, 8, Decoder::MutationRate);
+
+/// Every gene, in genome order.
+pub const GENES: [Gene; 7] =
+    [COLOR, DIGEST_MASK, REACTION_TIME, SEED_INVEST, W1, W2, MUTATION_RATE];
+
+/// Bits the genes use; `dna::SIZE` words hold them, and the rest is padding.
+pub const BITS: usize = GENES[GENES.len() - 1].end();
+
+/// The gene map as an aligned table, for `--genes table`.
+pub fn table() -> String {
+    let mut out = format!(
+        "{:<14}{:>7}{:>7}{:>7}{:>7}  decoder\n",
+        "gene", "offset", "width", "count", "bits"
+    );
+    for gene in GENES {
+        out += &format!(
+            "{:<14}{:>7}{:>7}{:>7}{:>7}  {}\n",
+            gene.name,
+            gene.offset,
+            gene.width,
+            gene.count,
+            gene.bits(),
+            gene.decoder.name()
+        );
+    }
+    out += &format!("{} bits in {} words, {} unused", BITS, SIZE, SIZE * 64 - BITS);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genes_tile_the_genome() {
+        let mut end = 0;
+        for gene in GENES {
+            assert_eq!(gene.offset, end, "{} follows on", gene.name);
+            assert!(gene.width <= 32);
+            end = gene.end();
+        }
+        assert_eq!(BITS, end);
+        assert_eq!(SIZE, BITS.div_ceil(64));
+        // where the brain has always started: moving it would change saved genomes
+        assert_eq!(W1.offset, 58);
+
+        let mut seq = [0; SIZE];
+        seq[0] = 0b111111 << 6;
+        assert_eq!(COLOR.read(&seq, 0), 0);
+        assert_eq!(COLOR.read(&seq, 1), 63);
+        assert_eq!(signed(0, 4), -1.0);
+        assert_eq!(signed(8, 4), 0.0);
+        let g = Genetics::default();
+        assert_eq!(REACTION_TIME.decode(&seq, 0, &g), g.reaction_floor_ms as f32);
+    }
+}
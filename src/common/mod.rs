@@ -1,4 +1,8 @@
 pub mod direction;
 pub mod coord;
+pub mod crossover;
 pub mod dna;
 pub mod brain;
+pub mod genome;
+pub mod mutation;
+pub mod topology;
@@ -0,0 +1,182 @@
+use bitvec::prelude::*;
+use rand::prelude::*;
+
+use crate::common::dna::SIZE;
+use crate::common::genome::{Decoder, BITS, GENES};
+use crate::config::Genetics;
+
+/// Mutate a genome on its way into a cell, whether by fertilisation or by
+/// colonisation, where `rate` is the parents' own heritable mutation rate.
+/// The operators run in a fixed order, each only drawing from `rng` when its
+/// rate is non-zero, and only touch the genes' bits:
+/// - 0..=`max_mutations` random bit flips;
+/// - every bit flips with `bit_rate`, or its gene's rate in `gene_rates`,
+///   plus `rate`;
+/// - every brain weight moves one step up or down with `perturb_rate`;
+/// - with `duplicate_rate`, a block of up to `block_max` bits is copied over
+///   another spot;
+/// - with `transpose_rate`, such a block is cut out and put back elsewhere.
+pub fn mutate<R: Rng>(seq: &mut [u64; SIZE], genetics: &Genetics, rate: f64, rng: &mut R) {
+    flip(seq, genetics.max_mutations, rng);
+    if rate > 0.0 || genetics.bit_rate > 0.0 || !genetics.gene_rates.is_empty() {
+        per_bit(seq, genetics, rate, rng);
+    }
+    if genetics.perturb_rate > 0.0 {
+        perturb(seq, genetics.perturb_rate, rng);
+    }
+    if genetics.duplicate_rate > 0.0 && rng.gen_bool(genetics.duplicate_rate) {
+        duplicate(seq, genetics.block_max, rng);
+    }
+    if genetics.transpose_rate > 0.0 && rng.gen_bool(genetics.transpose_rate) {
+        transpose(seq, genetics.block_max, rng);
+    }
+}
+
+// Flip 0..=max random bits.
+fn flip<R: Rng>(seq: &mut [u64; SIZE], max: u32, rng: &mut R) {
+    for _ in 0..rng.gen_range(0..=max) {
+        let b = rng.gen_range(0..BITS);
+        seq[b / 64] ^= 1u64 << (b % 64);
+    }
+}
+
+// Flip every bit with its gene's rate plus `extra`.
+fn per_bit<R: Rng>(seq: &mut [u64; SIZE], genetics: &Genetics, extra: f64, rng: &mut R) {
+    let bits = seq.view_bits_mut::<Lsb0>();
+    for gene in GENES {
+        let base = genetics.gene_rates.get(gene.name).copied().unwrap_or(genetics.bit_rate);
+        let rate = (base + extra).min(1.0);
+        if rate > 0.0 {
+            flip_each(&mut bits[gene.offset..gene.end()], rate, rng);
+        }
+    }
+}
+
+// Flip each bit with probability `rate`, jumping from one flip straight to
+// the next: the gaps between flips are geometric, so at the small rates
+// genomes carry this draws once per flip rather than once per bit.
+fn flip_each<R: Rng>(bits: &mut BitSlice<u64, Lsb0>, rate: f64, rng: &mut R) {
+    if rate >= 1.0 {
+        for i in 0..bits.len() {
+            let flipped = !bits[i];
+            bits.set(i, flipped);
+        }
+        return;
+    }
+    let log = (1.0 - rate).ln();
+    let mut i = 0;
+    loop {
+        // bits passed over before the next flip; `1 - gen()` keeps ln finite
+        let gap = ((1.0 - rng.gen::<f64>()).ln() / log).floor();
+        if gap >= (bits.len() - i) as f64 {
+            return;
+        }
+        i += gap as usize;
+        let flipped = !bits[i];
+        bits.set(i, flipped);
+        i += 1;
+    }
+}
+
+// Nudge each brain weight one step up or down with probability `rate`,
+// staying within its range — a small change to what the weight means, where
+// flipping its top bit would swing it by half its range.
+fn perturb<R: Rng>(seq: &mut [u64; SIZE], rate: f64, rng: &mut R) {
+    for gene in GENES.iter().filter(|g| g.decoder == Decoder::Weight) {
+        let top = (1u32 << gene.width) - 1;
+        for i in 0..gene.count {
+            if rng.gen_bool(rate) {
+                let raw = gene.read(seq, i);
+                let nudged = if rng.gen() { raw.saturating_sub(1) } else { (raw + 1).min(top) };
+                gene.write(seq, i, nudged);
+            }
+        }
+    }
+}
+
+// A random block length and start, fitting within the genes' bits.
+fn block<R: Rng>(max: usize, rng: &mut R) -> (usize, usize) {
+    let len = rng.gen_range(1..=max.min(BITS));
+    (len, rng.gen_range(0..=BITS - len))
+}
+
+// Copy a block of bits over another spot.
+fn duplicate<R: Rng>(seq: &mut [u64; SIZE], max: usize, rng: &mut R) {
+    let (len, from) = block(max, rng);
+    let to = rng.gen_range(0..=BITS - len);
+    seq.view_bits_mut::<Lsb0>().copy_within(from..from + len, to);
+}
+
+// Cut a block of bits out and reinsert it elsewhere, shifting the bits in
+// between to close the gap.
+fn transpose<R: Rng>(seq: &mut [u64; SIZE], max: usize, rng: &mut R) {
+    let (len, from) = block(max, rng);
+    let to = rng.gen_range(0..=BITS - len);
+    let bits = &mut seq.view_bits_mut::<Lsb0>()[..BITS];
+    if to < from {
+        bits[to..from + len].rotate_right(len);
+    } else {
+        bits[from..to + len].rotate_left(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::dna::hamming;
+    use crate::common::genome::{COLOR, W1};
+
+    #[test]
+    fn operators_change_what_they_should() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let still = Genetics {
+            max_mutations: 0,
+            ..Genetics::default()
+        };
+        let start: [u64; SIZE] = rng.gen();
+        let mut seq = start;
+        mutate(&mut seq, &still, 0.0, &mut rng);
+        assert_eq!(seq, start, "nothing to do at zero rates");
+
+        // a gene's own rate applies to that gene alone
+        let colour = Genetics {
+            gene_rates: [("color".to_string(), 1.0)].into(),
+            ..still.clone()
+        };
+        mutate(&mut seq, &colour, 0.0, &mut rng);
+        assert_eq!(hamming(&seq, &start), COLOR.bits() as u32);
+        let mut all = start;
+        mutate(&mut all, &still, 1.0, &mut rng);
+        assert_eq!(hamming(&all, &start), BITS as u32, "a rate of 1 flips every gene bit");
+
+        // skipping between flips still flips each bit at the rate given
+        let mut flips = 0;
+        for _ in 0..200 {
+            let mut seq = start;
+            flip_each(&mut seq.view_bits_mut::<Lsb0>()[..BITS], 0.01, &mut rng);
+            flips += hamming(&seq, &start);
+        }
+        let expected = 200.0 * BITS as f64 * 0.01;
+        assert!((flips as f64 - expected).abs() < expected * 0.1, "{} flips", flips);
+
+        // perturbation moves weights by one step
+        let mut seq = start;
+        perturb(&mut seq, 1.0, &mut rng);
+        for i in 0..W1.count {
+            let (before, after) = (W1.read(&start, i), W1.read(&seq, i));
+            assert!(before.abs_diff(after) <= 1);
+        }
+        assert_eq!(COLOR.read(&seq, 0), COLOR.read(&start, 0), "traits aren't weights");
+
+        // transposition only rearranges bits; neither touches the padding
+        let mut seq = start;
+        transpose(&mut seq, 64, &mut rng);
+        duplicate(&mut seq, 64, &mut rng);
+        transpose(&mut seq, BITS, &mut rng);
+        let mut moved = start;
+        transpose(&mut moved, 64, &mut StdRng::seed_from_u64(1));
+        let ones = |s: &[u64; SIZE]| s.view_bits::<Lsb0>()[..BITS].count_ones();
+        assert_eq!(ones(&moved), ones(&start));
+        assert_eq!(seq.view_bits::<Lsb0>()[BITS..], start.view_bits::<Lsb0>()[BITS..]);
+    }
+}
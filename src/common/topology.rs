@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::common::coord::Coord;
+
+/// What lies past the edge of the grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    /// Nothing: off-grid cells are a void that senses as 0 and swallows
+    /// effects.
+    #[default]
+    Bounded,
+    /// The grid wraps around; each edge touches the opposite one.
+    Torus,
+    /// The edges are mirrors: stepping one cell past an edge lands one cell
+    /// back inside it.
+    Reflect,
+}
+
+impl Topology {
+    /// The cell `pos` falls in on a grid of `size` cells, or None if it is
+    /// off a bounded grid.
+    pub fn resolve(self, pos: Coord, size: Coord) -> Option<Coord> {
+        let (x, y, w, h) = (pos.x, pos.y, size.x, size.y);
+        let (x, y) = match self {
+            Topology::Bounded => {
+                if !(0..w).contains(&x) || !(0..h).contains(&y) {
+                    return None;
+                }
+                (x, y)
+            }
+            Topology::Torus => (x.rem_euclid(w), y.rem_euclid(h)),
+            Topology::Reflect => (reflect(x, w), reflect(y, h)),
+        };
+        Some(Coord::new(x, y))
+    }
+}
+
+// Mirror `i` into 0..n about the centres of the first and last cells.
+fn reflect(i: i32, n: i32) -> i32 {
+    if n == 1 {
+        return 0;
+    }
+    let period = 2 * (n - 1);
+    let m = i.rem_euclid(period);
+    if m < n {
+        m
+    } else {
+        period - m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(t: Topology, x: i32, y: i32) -> Option<(i32, i32)> {
+        t.resolve(Coord::new(x, y), Coord::new(4, 3)).map(|c| (c.x, c.y))
+    }
+
+    #[test]
+    fn edges() {
+        assert_eq!(at(Topology::Bounded, 1, 2), Some((1, 2)));
+        assert_eq!(at(Topology::Bounded, -1, 0), None);
+        assert_eq!(at(Topology::Bounded, 4, 0), None);
+
+        assert_eq!(at(Topology::Torus, -1, 3), Some((3, 0)));
+        assert_eq!(at(Topology::Torus, 4, -1), Some((0, 2)));
+
+        assert_eq!(at(Topology::Reflect, -1, 3), Some((1, 1)));
+        assert_eq!(at(Topology::Reflect, 4, -2), Some((2, 2)));
+        assert_eq!(at(Topology::Reflect, 2, 1), Some((2, 1)));
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::bus::Overflow;
+use crate::common::crossover::Method;
+use crate::common::dna::{MUTATION_RATE_MAX, REACTION_FLOOR_MS, SEED_INVEST_MAX};
+use crate::common::genome::{BITS, GENES};
+use crate::common::topology::Topology;
+use crate::grid::Backend;
+
+/// Every tunable of a run, loaded from a TOML file at startup. Any section or
+/// field left out keeps its default, and the defaults reproduce the built-in
+/// world exactly — an empty file is a valid config.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub world: World,
+    pub engine: Engine,
+    pub bus: Bus,
+    pub dot: DotParams,
+    pub genetics: Genetics,
+    pub stats: Stats,
+    pub species: Species,
+    pub capture: Capture,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct World {
+    /// Grid size in cells.
+    pub width: u32,
+    pub height: u32,
+    /// Window pixels per cell.
+    pub scale: u8,
+    /// Random genomes are dropped on every cell where x and y are both
+    /// `seed_offset` modulo `seed_spacing`...
+    pub seed_spacing: u32,
+    pub seed_offset: u32,
+    /// ...this many per seeding site.
+    pub seeds_per_site: u32,
+    /// What dots sense and reach past the edges: bounded, torus or reflect.
+    pub topology: Topology,
+    /// How the scene stores its cells: a dense array or a sharded map.
+    pub storage: Backend,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World {
+            width: 320,
+            height: 180,
+            scale: 4,
+            seed_spacing: 9,
+            seed_offset: 4,
+            seeds_per_site: 2,
+            topology: Topology::Bounded,
+            storage: Backend::Dense,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Engine {
+    /// Tasks applying effects from the channel (async engine).
+    pub propagators: usize,
+    /// Viewer updates and frames per second.
+    pub ups: u64,
+    pub max_fps: u64,
+    /// Virtual milliseconds per step of the parallel engine; reaction times
+    /// are rounded up to whole steps.
+    pub step_ms: u64,
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine {
+            propagators: 4,
+            ups: 12,
+            max_fps: 30,
+            step_ms: REACTION_FLOOR_MS,
+        }
+    }
+}
+
+/// The effect channel under the async engine.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bus {
+    /// Most effects queued at once.
+    pub capacity: usize,
+    /// What a send does when the bus is full.
+    pub policy: Overflow,
+}
+
+impl Default for Bus {
+    fn default() -> Bus {
+        Bus {
+            capacity: 65_536,
+            policy: Overflow::Block,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DotParams {
+    /// Added to a living dot's age every tick; its upkeep is its age.
+    pub aging_rate: f32,
+    /// Energy an empty cell regrows every tick.
+    pub regrowth_rate: f32,
+    /// Energy a digest drains from its target, before the digester's mask.
+    pub digest_strength: f32,
+}
+
+impl Default for DotParams {
+    fn default() -> DotParams {
+        DotParams {
+            aging_rate: 0.005,
+            regrowth_rate: 0.005,
+            digest_strength: 0.1,
+        }
+    }
+}
+
+/// How genomes decode, recombine and mutate (see `mutation::mutate`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Genetics {
+    /// See `dna::REACTION_FLOOR_MS`.
+    pub reaction_floor_ms: u64,
+    /// See `dna::SEED_INVEST_MAX`.
+    pub seed_invest_max: f32,
+    /// See `dna::MUTATION_RATE_MAX`.
+    pub mutation_rate_max: f32,
+    /// Each genome entering a cell gets 0..=this many random bit flips.
+    pub max_mutations: u32,
+    /// How parents' genomes recombine (see `common::crossover`).
+    pub crossover: Method,
+    /// The chance each bit of a genome entering a cell flips...
+    pub bit_rate: f64,
+    /// ...or, for the genes named here (see `--genes`), this chance.
+    pub gene_rates: BTreeMap<String, f64>,
+    /// The chance each brain weight moves one step up or down.
+    pub perturb_rate: f64,
+    /// The chance a genome has a block of bits copied over another spot...
+    pub duplicate_rate: f64,
+    /// ...or cut out and reinserted elsewhere...
+    pub transpose_rate: f64,
+    /// ...a block being up to this many bits long.
+    pub block_max: usize,
+}
+
+impl Default for Genetics {
+    fn default() -> Genetics {
+        Genetics {
+            reaction_floor_ms: REACTION_FLOOR_MS,
+            seed_invest_max: SEED_INVEST_MAX,
+            mutation_rate_max: MUTATION_RATE_MAX,
+            max_mutations: 3,
+            crossover: Method::Uniform,
+            bit_rate: 0.0,
+            gene_rates: BTreeMap::new(),
+            perturb_rate: 0.0,
+            duplicate_rate: 0.0,
+            transpose_rate: 0.0,
+            block_max: 32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stats {
+    /// Simulation milliseconds between samples written with `--stats`.
+    pub every_ms: u64,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats { every_ms: 1000 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Species {
+    /// Most bits a genome may differ from a species' representative and
+    /// still belong to it. Unrelated genomes differ in about half their bits.
+    pub threshold: u32,
+}
+
+impl Default for Species {
+    fn default() -> Species {
+        Species { threshold: 256 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Capture {
+    /// Scene ticks between frames captured with `--frames` or `--gif`.
+    pub every_ticks: u64,
+    /// How long each frame shows in the GIF.
+    pub frame_delay_ms: u64,
+}
+
+impl Default for Capture {
+    fn default() -> Capture {
+        Capture {
+            every_ticks: 50_000,
+            frame_delay_ms: 100,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let w = &self.world;
+        let side = 1..=u16::MAX as u32;
+        if !side.contains(&w.width) || !side.contains(&w.height) {
+            return Err(format!("world size {}x{} must be within 1..=65535", w.width, w.height));
+        }
+        if w.scale == 0 {
+            return Err("world.scale must be at least 1".to_string());
+        }
+        if w.seed_spacing == 0 || w.seed_offset >= w.seed_spacing {
+            return Err(
+                "world.seed_offset must be less than a non-zero world.seed_spacing".to_string(),
+            );
+        }
+        if self.engine.propagators == 0 || self.engine.ups == 0 || self.engine.max_fps == 0 {
+            return Err(
+                "engine.propagators, engine.ups and engine.max_fps must be non-zero".to_string(),
+            );
+        }
+        if self.bus.capacity == 0 {
+            return Err("bus.capacity must be at least 1".to_string());
+        }
+        if self.engine.step_ms == 0 {
+            return Err("engine.step_ms must be at least 1".to_string());
+        }
+        for (name, v) in [
+            ("dot.aging_rate", self.dot.aging_rate),
+            ("dot.regrowth_rate", self.dot.regrowth_rate),
+            ("dot.digest_strength", self.dot.digest_strength),
+            ("genetics.seed_invest_max", self.genetics.seed_invest_max),
+            ("genetics.mutation_rate_max", self.genetics.mutation_rate_max),
+        ] {
+            if !(0.0..=1.0).contains(&v) {
+                return Err(format!("{} must be within [0, 1], got {}", name, v));
+            }
+        }
+        let g = &self.genetics;
+        for (name, v) in [
+            ("genetics.bit_rate", g.bit_rate),
+            ("genetics.perturb_rate", g.perturb_rate),
+            ("genetics.duplicate_rate", g.duplicate_rate),
+            ("genetics.transpose_rate", g.transpose_rate),
+        ] {
+            if !(0.0..=1.0).contains(&v) {
+                return Err(format!("{} must be within [0, 1], got {}", name, v));
+            }
+        }
+        for (name, &v) in &g.gene_rates {
+            if !GENES.iter().any(|gene| gene.name == name) {
+                return Err(format!("genetics.gene_rates: no gene named '{}'", name));
+            }
+            if !(0.0..=1.0).contains(&v) {
+                let name = format!("genetics.gene_rates.{}", name);
+                return Err(format!("{} must be within [0, 1], got {}", name, v));
+            }
+        }
+        if !(1..=BITS).contains(&g.block_max) {
+            return Err(format!("genetics.block_max must be within 1..={}", BITS));
+        }
+        if self.stats.every_ms == 0 {
+            return Err("stats.every_ms must be at least 1".to_string());
+        }
+        if self.capture.every_ticks == 0 {
+            return Err("capture.every_ticks must be at least 1".to_string());
+        }
+        // the floor exists so metabolism can't evolve into a busy loop
+        if self.genetics.reaction_floor_ms == 0 {
+            return Err("genetics.reaction_floor_ms must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_is_the_default_world() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::default().validate().is_ok());
+        // the example file documents the defaults, so it must match them
+        assert_eq!(Config::parse(include_str!("../dots.toml")).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn partial_sections_keep_defaults() {
+        let config =
+            Config::parse("[world]\nwidth = 64\n\n[genetics]\nmax_mutations = 0\n").unwrap();
+        assert_eq!(config.world.width, 64);
+        assert_eq!(config.world.height, World::default().height);
+        assert_eq!(config.genetics.max_mutations, 0);
+        assert_eq!(config.dot, DotParams::default());
+        let torus = Config::parse("[world]\ntopology = \"torus\"\n").unwrap();
+        assert_eq!(torus.world.topology, Topology::Torus);
+        let two = Config::parse("[genetics]\ncrossover = \"two_point\"\n").unwrap();
+        assert_eq!(two.genetics.crossover, Method::TwoPoint);
+        let rates = Config::parse("[genetics]\ngene_rates = { w2 = 0.01 }\n").unwrap();
+        assert_eq!(rates.genetics.gene_rates["w2"], 0.01);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(Config::parse("[world]\nwidth = 0\n").is_err());
+        assert!(Config::parse("[world]\nseed_offset = 9\n").is_err());
+        assert!(Config::parse("[dot]\naging_rate = -0.1\n").is_err());
+        assert!(Config::parse("[genetics]\nreaction_floor_ms = 0\n").is_err());
+        assert!(Config::parse("[genetics]\nbit_rate = 2.0\n").is_err());
+        assert!(Config::parse("[genetics]\ngene_rates = { tail = 0.1 }\n").is_err());
+        assert!(Config::parse("[world]\nwidht = 10\n").is_err(), "typos are caught");
+        assert!(Config::parse("[world]\ntopology = \"sphere\"\n").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::Read;
+
+use crate::common::brain::{Brain, N_HID};
+use crate::common::dna::Dna;
+use crate::config::Config;
+use crate::inspector::DIRECTIONS;
+
+/// Decode the genome in `text` (see `Dna::format`), or on stdin if `text` is
+/// "-", and print its traits and brain weights. Traits depend on the
+/// genetics in `config`.
+pub fn run(text: &str, config: &Config) -> Result<(), String> {
+    let text = match text {
+        "-" => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+            input
+        }
+        text => text.to_string(),
+    };
+    let dna = Dna::parse(&text, &config.genetics)?;
+    println!("{}", report(&dna).join("\n"));
+    Ok(())
+}
+
+/// The traits, one per line, then the weight matrices: `w1` with a row per
+/// hidden unit and a column per input (each neighbour's r, g and b, own
+/// energy, bias), `w2` with a row per output and a column per hidden unit.
+pub fn report(dna: &Dna) -> Vec<String> {
+    let rgb = |c: [f32; 3]| format!("{:.3} {:.3} {:.3}", c[0], c[1], c[2]);
+    let mut lines = vec![
+        format!("color          {}", rgb(dna.color)),
+        format!("digest_mask    {}", rgb(dna.digest_mask)),
+        format!("reaction_time  {}ms", dna.reaction_time.as_millis()),
+        format!("seed_invest    {:.4}", dna.seed_invest),
+        format!("mutation_rate  {:.6}", dna.mutation_rate),
+        format!("display_color  {}", rgb(dna.display_color)),
+    ];
+    let brain = Brain::from_seq(&dna.seq);
+    let row = |label: &str, weights: &[f32]| {
+        weights.iter().fold(format!("{:<9}", label), |row, w| row + &format!("{:>7.2}", w))
+    };
+    let mut inputs: Vec<String> = DIRECTIONS
+        .iter()
+        .flat_map(|d| ["r", "g", "b"].map(|c| format!("{}.{}", d, c)))
+        .collect();
+    inputs.extend(["energy".to_string(), "bias".to_string()]);
+    let hidden: Vec<String> = (0..N_HID).map(|h| format!("h{}", h)).collect();
+    let heading = |columns: &[String]| {
+        columns.iter().fold(format!("{:<9}", ""), |row, c| row + &format!("{:>7}", c))
+    };
+
+    lines.push(String::new());
+    lines.push("w1 (hidden x input)".to_string());
+    lines.push(heading(&inputs));
+    for (h, weights) in brain.w1().iter().enumerate() {
+        lines.push(row(&hidden[h], weights));
+    }
+    lines.push(String::new());
+    lines.push("w2 (output x hidden)".to_string());
+    lines.push(heading(&hidden));
+    let outputs = DIRECTIONS
+        .iter()
+        .map(|d| format!("digest {}", d))
+        .chain(DIRECTIONS.iter().map(|d| format!("seed {}", d)))
+        .chain(["idle".to_string()]);
+    for (label, weights) in outputs.zip(brain.w2()) {
+        lines.push(row(&label, weights));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::brain::N_OUT;
+    use crate::common::dna::SIZE;
+    use crate::config::Genetics;
+
+    #[test]
+    fn report_lists_traits_and_weights() {
+        let dna = Dna::new([0xfedc_ba98_7654_3210_u64; SIZE], &Genetics::default());
+        let lines = report(&Dna::parse(&dna.format(), &Genetics::default()).unwrap());
+        assert!(lines[0].starts_with("color "));
+        assert_eq!(lines[2], format!("reaction_time  {}ms", dna.reaction_time.as_millis()));
+        // six traits, then each matrix: a gap, a title, a heading and its rows
+        assert_eq!(lines.len(), 6 + 3 + N_HID + 3 + N_OUT);
+        assert!(lines[8].trim_start().starts_with("N.r    N.g    N.b   NE.r"));
+        assert!(lines.last().unwrap().starts_with("idle "));
+    }
+}
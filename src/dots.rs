@@ -1,114 +1,218 @@
 use crate::action::Action;
+use crate::bus::Bus;
 use crate::common::coord::Coord;
 use crate::common::direction::Direction;
 use crate::common::brain::{Brain, N_IN, N_OUT};
-use crate::common::dna::{self, combine, Dna};
+use crate::common::dna::{self, combine, Dna, SIZE};
+use crate::common::mutation;
+use crate::config::Genetics;
 use crate::effect::Effect;
+use crate::inspector::Inspection;
+use crate::layer::Vitals;
+use crate::lineage::Lineage;
 use crate::scene::Scene;
-use flume::Sender;
+use crate::shutdown::Token;
 use futures::lock::Mutex;
-use std::sync::atomic::{AtomicU32, Ordering};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::task::{spawn, JoinHandle};
-use tokio::time::{sleep, Duration, Instant};
+use tokio::time::Duration;
 
-/// A grid cell: the simulation state behind an async Mutex, plus two lock-free
-/// RGBA8 snapshots refreshed after every mutation. `sense` is the phenotype
-/// other dots perceive; `render` is the whole-genome colour shown to the viewer.
-/// Both are read without taking the dot's lock.
+/// How often a cell without a reaction time (empty, or colonised but not yet
+/// fertilised) ticks to regrow energy.
+const GROWTH_RATE: Duration = Duration::from_millis(u8::MAX as u64 * 4);
+
+/// How walls look, to the viewer and to the dots around them.
+const WALL_RGB: [f32; 3] = [0.5, 0.5, 0.5];
+
+/// A grid cell: the simulation state behind an async Mutex, plus lock-free
+/// snapshots refreshed after every mutation. `sense` is the RGBA8 phenotype
+/// other dots perceive; `render` is the RGBA8 whole-genome colour shown to the
+/// viewer; `vitals` is a packed `Vitals` for the viewer's other layers. All
+/// are read without taking the dot's lock.
 pub struct Cell {
     pub sense: AtomicU32,
     pub render: AtomicU32,
+    pub vitals: AtomicU64,
+    /// The species the stats recorder last classified this cell's genome into,
+    /// or 0. Written by `Sample::take`, not by `refresh_snapshots`.
+    pub species: AtomicU64,
     pub dot: Mutex<Dot>,
 }
 
 impl Cell {
-    // Recompute both snapshots from the dot's current state.
+    // Recompute the snapshots from the dot's current state.
     pub fn refresh_snapshots(&self, dot: &Dot) {
         self.sense.store(dot.pack_sense(), Ordering::Relaxed);
         self.render.store(dot.pack_render(), Ordering::Relaxed);
+        self.vitals.store(dot.vitals().pack(), Ordering::Relaxed);
     }
 }
 
 pub struct DotFactory {
-    tx: Sender<(Coord, Arc<Effect>)>,
+    bus: Arc<Bus>,
     scene: Arc<Scene>,
+    // hands every new dot its own generator, drawn from the master seed, so a
+    // dot's random choices don't depend on which thread happens to run it
+    rng: std::sync::Mutex<StdRng>,
+    // tells the tickers this factory starts when to stop
+    stop: Token,
 }
 
 impl DotFactory {
-    pub fn new(tx: Sender<(Coord, Arc<Effect>)>, scene: Arc<Scene>) -> DotFactory {
-        DotFactory { tx, scene }
+    pub fn new(bus: Arc<Bus>, scene: Arc<Scene>, rng: StdRng) -> DotFactory {
+        DotFactory {
+            bus,
+            scene,
+            rng: std::sync::Mutex::new(rng),
+            stop: Token::never(),
+        }
     }
 
-    pub async fn create(
-        &self,
-        pos: Coord,
-        seq: Option<[u64; dna::SIZE]>,
-        energy: f32,
-    ) -> Arc<Cell> {
-        let dna = match seq {
-            Some(seq) => Some(Dna::new(seq)),
-            None => None,
-        };
+    /// Have every ticker this factory starts end when `stop` is cancelled.
+    pub fn stopped_by(mut self, stop: Token) -> DotFactory {
+        self.stop = stop;
+        self
+    }
+
+    fn next_rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.rng.lock().unwrap().gen())
+    }
+
+    fn wrap(&self, dot: Dot) -> Arc<Cell> {
         let cell = Arc::new(Cell {
             sense: AtomicU32::new(0),
             render: AtomicU32::new(0),
-            dot: Mutex::new(Dot::new(pos, dna, energy, self.tx.clone())),
+            vitals: AtomicU64::new(0),
+            species: AtomicU64::new(0),
+            dot: Mutex::new(dot),
         });
+        let dot = cell.dot.try_lock().expect("a new cell is unshared");
+        cell.refresh_snapshots(&dot);
+        drop(dot);
+        cell
+    }
 
+    /// Build a cell without starting its ticker — for engines that drive
+    /// ticks themselves (see `stepper`).
+    pub fn build(&self, pos: Coord, seq: Option<[u64; dna::SIZE]>, energy: f32) -> Arc<Cell> {
+        let genetics = &self.scene.config().genetics;
+        let dna = seq.map(|seq| Dna::new(seq, genetics));
+        self.wrap(Dot::new(pos, dna, energy, self.next_rng()))
+    }
+
+    /// Rebuild a cell from saved state, without starting its ticker.
+    pub fn restore(&self, state: &DotState) -> Arc<Cell> {
+        let genetics = &self.scene.config().genetics;
+        self.wrap(Dot::from_state(state, genetics, self.next_rng()))
+    }
+
+    /// Start the cell's ticker task on the tokio runtime.
+    pub async fn start(&self, cell: &Arc<Cell>) {
         let ptr = cell.clone();
         let scene = self.scene.clone();
+        let bus = self.bus.clone();
+        let stop = self.stop.clone();
         let mut dot = cell.dot.lock().await;
-        cell.refresh_snapshots(&dot);
         dot.task_tick = Some(spawn(async move {
-            ticker(ptr, scene).await;
+            ticker(ptr, scene, bus, stop).await;
         }));
-        drop(dot);
-        return cell;
+    }
+
+    pub async fn create(
+        &self,
+        pos: Coord,
+        seq: Option<[u64; dna::SIZE]>,
+        energy: f32,
+    ) -> Arc<Cell> {
+        let cell = self.build(pos, seq, energy);
+        self.start(&cell).await;
+        cell
+    }
+
+    /// Fill the scene with empty dots and queue random genomes on the seeding
+    /// lattice (`config.world`) to start the population. Tickers are only
+    /// started when `tick` is set; otherwise an engine must drive the cells.
+    pub async fn populate(&self, rng: &mut StdRng, tick: bool) {
+        let world = &self.scene.config().world;
+        let genetics = &self.scene.config().genetics;
+        let (spacing, offset) = (world.seed_spacing as i32, world.seed_offset as i32);
+        let size = self.scene.size();
+        for i in 0..size.x as usize * size.y as usize {
+            let pos = Coord::from_index(i, size);
+
+            let cell = if tick {
+                self.create(pos, None, 1.0).await
+            } else {
+                self.build(pos, None, 1.0)
+            };
+            self.scene.push_dot(pos, cell);
+            if pos.x % spacing == offset && pos.y % spacing == offset {
+                for _ in 0..world.seeds_per_site {
+                    let mut dna = Dna::new(rng.gen::<[u64; SIZE]>(), genetics);
+                    dna.lineage = self.scene.ancestry.birth([None, None], 0);
+                    self.bus.send((pos, Arc::new(Effect::SEED(dna, 0.0)))).await;
+                }
+            }
+        }
     }
 }
 
-async fn ticker(cell: Arc<Cell>, scene: Arc<Scene>) {
-    let growth_rate = Duration::from_millis(u8::MAX as u64 * 4);
-    let ticker = sleep(Duration::from_millis(0));
-    tokio::pin!(ticker);
+// Tick on the scene's clock until `stop` is cancelled. A tick in progress,
+// including sending its effect, always completes first.
+async fn ticker(cell: Arc<Cell>, scene: Arc<Scene>, bus: Arc<Bus>, mut stop: Token) {
+    let mut due = scene.clock.now();
     loop {
         tokio::select! {
-            () = &mut ticker => {
+            () = scene.clock.sleep_until(due) => {
                 let mut dot = cell.dot.lock().await;
-                if let Some(_) = dot.dna {
-                    dot.age += 0.005;
-                    dot.energy = f32::max(0.0,f32::min(1.0,dot.energy-dot.age));
-                    if dot.energy == 0.0 {
-                        dot.dna = None;
-                        dot.reaction_time = None;
-                        dot.age = 0.0;
-                        dot.refresh_brain();
-                    } else {
-                        let senses = dot.neighbors().map(|c| scene.sense(c));
-                        dot.act(senses).await;
-                    }
-                } else {
-                    dot.energy += 0.005;
-                }
+                let effect = dot.tick(&scene);
 
                 // refresh the lock-free snapshots after mutating
                 cell.refresh_snapshots(&dot);
-                ticker.as_mut().reset(Instant::now() + dot.reaction_time.unwrap_or(growth_rate));
+                due = scene.clock.now() + dot.next_tick_in();
+                drop(dot);
+                if let Some(effect) = effect {
+                    bus.send(effect).await;
+                }
             }
+            () = stop.cancelled() => break,
         }
     }
 }
 
+/// Everything needed to bring a dot back as it was (see `snapshot`). The
+/// dot's random generator isn't kept; a restored dot draws a fresh one.
+#[derive(Serialize, Deserialize)]
+pub struct DotState {
+    pub pos: Coord,
+    /// The genome's sequence; its traits are decoded again on restore.
+    pub dna: Option<[u64; SIZE]>,
+    pub lineage: Lineage,
+    pub energy: f32,
+    pub age: f32,
+    pub reaction_time: Option<Duration>,
+    pub wall: bool,
+}
+
 pub struct Dot {
     pub pos: Coord,
     pub dna: Option<Dna>,
     energy: f32,
     age: f32,
     reaction_time: Option<Duration>,
-    tx: Sender<(Coord, Arc<Effect>)>,
+    rng: StdRng,
     pub task_tick: Option<JoinHandle<()>>,
     brain: Option<Brain>,
+    // the brain's output on the latest decision and the action it chose,
+    // kept for the inspector and the viewer's layers
+    scores: Option<[f32; N_OUT]>,
+    action: Option<Action>,
+    // drawn by the viewer's wall tool: holds nothing and ignores effects
+    wall: bool,
 }
 
 // Read the net's choice: the highest-scoring output selects both the action
@@ -131,25 +235,83 @@ fn decide(out: &[f32; N_OUT]) -> Option<(Action, Direction)> {
 }
 
 impl Dot {
-    pub fn new(pos: Coord, dna: Option<Dna>, energy: f32, tx: Sender<(Coord, Arc<Effect>)>) -> Dot {
-        let dot = Dot {
+    pub fn new(pos: Coord, dna: Option<Dna>, energy: f32, rng: StdRng) -> Dot {
+        Dot {
             pos,
             dna,
             energy,
             age: 0.0,
             reaction_time: None,
-            tx,
+            rng,
             task_tick: None,
             brain: dna.map(|d| Brain::from_seq(&d.seq)),
-        };
-        return dot;
+            scores: None,
+            action: None,
+            wall: false,
+        }
     }
 
-    pub async fn act(&mut self, senses: [u32; 8]) {
-        let brain = match &self.brain {
-            Some(brain) => brain,
-            None => return,
-        };
+    pub fn from_state(state: &DotState, genetics: &Genetics, rng: StdRng) -> Dot {
+        let dna = state.dna.map(|seq| Dna {
+            lineage: state.lineage,
+            ..Dna::new(seq, genetics)
+        });
+        let mut dot = Dot::new(state.pos, dna, state.energy, rng);
+        dot.age = state.age;
+        dot.reaction_time = state.reaction_time;
+        dot.wall = state.wall;
+        dot
+    }
+
+    pub fn state(&self) -> DotState {
+        DotState {
+            pos: self.pos,
+            dna: self.dna.map(|d| d.seq),
+            lineage: self.dna.map(|d| d.lineage).unwrap_or_default(),
+            energy: self.energy,
+            age: self.age,
+            reaction_time: self.reaction_time,
+            wall: self.wall,
+        }
+    }
+
+    /// One metabolic tick: a dot with a genome ages and pays its upkeep, dying
+    /// at zero energy, or else senses its neighbourhood and acts; an empty
+    /// cell regrows and a wall does nothing. Returns the effect the dot's
+    /// action emits, if any, for the engine to deliver.
+    pub fn tick(&mut self, scene: &Scene) -> Option<(Coord, Arc<Effect>)> {
+        let params = &scene.config().dot;
+        scene.ticks.fetch_add(1, Ordering::Relaxed);
+        if self.wall {
+            None
+        } else if self.dna.is_some() {
+            self.age += params.aging_rate;
+            self.energy = (self.energy - self.age).clamp(0.0, 1.0);
+            if self.energy == 0.0 {
+                self.die(scene, &scene.deaths);
+                None
+            } else {
+                let senses = self.neighbors(scene).map(|c| c.map_or(0, |c| scene.sense(c)));
+                self.act(senses, scene)
+            }
+        } else {
+            self.energy += params.regrowth_rate;
+            None
+        }
+    }
+
+    /// Delay until this dot's next tick: its reaction time, or the regrowth
+    /// cadence if it has none.
+    pub fn next_tick_in(&self) -> Duration {
+        self.reaction_time.unwrap_or(GROWTH_RATE)
+    }
+
+    /// Decide on an action from the senses and return the effect it sends;
+    /// a digest drains `dot.digest_strength` from its target. An action aimed
+    /// off a bounded world sends nothing, though a seed is still paid for.
+    pub fn act(&mut self, senses: [u32; 8], scene: &Scene) -> Option<(Coord, Arc<Effect>)> {
+        let digest_strength = scene.config().dot.digest_strength;
+        let brain = self.brain.as_ref()?;
 
         // raw perception: each neighbour's (r, g, b), own energy, bias unit.
         let mut input = [0.0f32; N_IN];
@@ -161,54 +323,65 @@ impl Dot {
         input[24] = self.energy;
         input[25] = 1.0;
 
-        let decision = decide(&brain.forward(&input));
+        let scores = brain.forward(&input);
+        self.scores = Some(scores);
+        let decision = decide(&scores);
+        self.action = Some(decision.as_ref().map_or(Action::IDLE, |(action, _)| *action));
         let dna = self.dna.unwrap();
         match decision {
-            None => {} // IDLE
-            Some((Action::DIGEST, direction)) => {
-                self.tx
-                    .send_async((
-                        self.reach(direction, 1.0),
-                        Arc::new(Effect::ENERGY(-0.1, Some(dna.digest_mask), Some(self.pos))),
-                    ))
-                    .await
-                    .unwrap();
-            }
+            None => None, // IDLE
+            Some((Action::DIGEST, direction)) => Some((
+                self.reach(direction, 1, scene)?,
+                Arc::new(Effect::ENERGY(-digest_strength, Some(dna.digest_mask), Some(self.pos))),
+            )),
             Some((Action::SEED, direction)) => {
                 // pay the seed cost up front, whatever the dot can afford; it
                 // rides along to provision the offspring if the seed lands.
                 let invest = dna.seed_invest.min(self.energy);
                 self.energy -= invest;
-                self.tx
-                    .send_async((self.reach(direction, 1.0), Arc::new(Effect::SEED(dna, invest))))
-                    .await
-                    .unwrap();
+                Some((self.reach(direction, 1, scene)?, Arc::new(Effect::SEED(dna, invest))))
             }
-            Some((Action::IDLE, _)) => {}
+            Some((Action::IDLE, _)) => None,
+        }
+    }
+
+    // Empty the cell, counting a death in `count` if it held a genome:
+    // `scene.deaths` for starvation, `scene.killed` for an edit.
+    fn die(&mut self, scene: &Scene, count: &AtomicU64) {
+        if let Some(dna) = self.dna.take() {
+            count.fetch_add(1, Ordering::Relaxed);
+            scene.ancestry.depart(dna.lineage.id);
         }
+        self.reaction_time = None;
+        self.age = 0.0;
+        self.refresh_brain();
     }
 
     // Re-decode the brain from the current DNA. Called whenever DNA changes so
     // the brain always matches the genome (None when the dot is dead/empty).
     fn refresh_brain(&mut self) {
         self.brain = self.dna.map(|d| Brain::from_seq(&d.seq));
+        self.scores = None;
+        self.action = None;
     }
 
     // The 8 neighbour coords in net-output direction order, so senses[i] lines
     // up with Direction::from_index(i) and with where the dot will act.
-    fn neighbors(&self) -> [Coord; 8] {
-        let mut n = [self.pos; 8];
+    // None where the world's topology leaves no cell.
+    fn neighbors(&self, scene: &Scene) -> [Option<Coord>; 8] {
+        let mut n = [None; 8];
         for (i, c) in n.iter_mut().enumerate() {
-            *c = self.reach(Direction::from_index(i), 1.0);
+            *c = self.reach(Direction::from_index(i), 1, scene);
         }
         n
     }
 
     // Pack appearance into RGBA8. A live dot shows `alive_rgb` fully opaque; a
-    // dead/empty cell shows white with opacity ramping from its energy. Position
-    // is the map key, so it isn't packed.
+    // dead/empty cell shows white with opacity ramping from its energy, and a
+    // wall opaque grey. Position is the map key, so it isn't packed.
     fn pack(&self, alive_rgb: [f32; 3]) -> u32 {
         let (rgb, alpha): ([f32; 3], f32) = match self.dna {
+            _ if self.wall => (WALL_RGB, 1.0),
             Some(_) => (alive_rgb, 1.0),
             None => ([1.0, 1.0, 1.0], 0.5 + self.energy / 2.0),
         };
@@ -221,13 +394,37 @@ impl Dot {
         self.pack(self.dna.map_or([1.0; 3], |d| d.color))
     }
 
+    /// What the viewer's layers other than colour show (see `layer`).
+    pub fn vitals(&self) -> Vitals {
+        Vitals {
+            energy: self.energy,
+            age: self.age,
+            reaction_ms: self
+                .dna
+                .map_or(0, |d| d.reaction_time.as_millis().min(u16::MAX as u128) as u16),
+            seed_invest: self.dna.map_or(0.0, |d| d.seed_invest),
+            action: self.action,
+            genome: self.dna.is_some(),
+        }
+    }
+
     /// What the viewer sees: a colour derived from the whole genome, so
     /// genetically-similar dots look alike (the phenotype is hidden from view).
     pub fn pack_render(&self) -> u32 {
         self.pack(self.dna.map_or([1.0; 3], |d| d.display_color))
     }
 
-    pub async fn apply_effect(&mut self, effect: Arc<Effect>) {
+    /// Apply an incoming effect. Returns the effect sent back in response (the
+    /// energy a digest drains is credited back to the digester), if any.
+    pub fn apply_effect(
+        &mut self,
+        effect: Arc<Effect>,
+        scene: &Scene,
+    ) -> Option<(Coord, Arc<Effect>)> {
+        let genetics = &scene.config().genetics;
+        if self.wall && !matches!(*effect, Effect::WALL(_)) {
+            return None;
+        }
         match *effect {
             Effect::ENERGY(eff, mask, pos) => {
                 let mask = mask.unwrap_or([1.0, 1.0, 1.0]);
@@ -248,72 +445,120 @@ impl Dot {
                     delta = 1.0 - self.energy
                 }
 
-                self.energy = f32::max(0.0, f32::min(1.0, self.energy + eff));
-                if let Some(pos) = pos {
-                    self.tx
-                        .send_async((pos, Arc::new(Effect::ENERGY(-delta, None, None))))
-                        .await
-                        .unwrap();
-                }
+                self.energy = (self.energy + eff).clamp(0.0, 1.0);
+                pos.map(|pos| (pos, Arc::new(Effect::ENERGY(-delta, None, None))))
             }
             Effect::SEED(other, provision) => {
                 if !self.is_alive() {
+                    let tick = scene.ticks.load(Ordering::Relaxed);
+                    // genomes from outside the scene's lineage (id 0) are founders
+                    let known = |l: Lineage| (l.id != 0).then_some(l.id);
                     if let Some(mine) = self.dna {
                         // fertilise: recombine, and inherit metabolism from the child
-                        let child = combine(mine, other);
+                        let mut child = combine(mine, other, genetics, &mut self.rng);
+                        let parents = [known(mine.lineage), known(other.lineage)];
+                        child.lineage = scene.ancestry.birth(parents, tick);
+                        scene.ancestry.arrive(&child.lineage);
+                        scene.ancestry.depart(mine.lineage.id);
+                        scene.births.fetch_add(1, Ordering::Relaxed);
                         self.reaction_time = Some(child.reaction_time);
                         self.dna = Some(child);
                     } else {
-                        self.dna = Some(Dna::new(other.seq));
+                        // colonise: the cell carries a copy of the genome, mutated
+                        // like a child's, and keeps its lineage
+                        let lineage = match known(other.lineage) {
+                            Some(_) => other.lineage,
+                            None => scene.ancestry.birth([None, None], tick),
+                        };
+                        scene.ancestry.arrive(&lineage);
+                        let mut seq = other.seq;
+                        let rate = other.mutation_rate as f64;
+                        mutation::mutate(&mut seq, genetics, rate, &mut self.rng);
+                        self.dna = Some(Dna {
+                            lineage,
+                            ..Dna::new(seq, genetics)
+                        });
                     }
                     // receive the parent's investment as starting energy
                     self.energy = (self.energy + provision).min(1.0);
                     self.refresh_brain();
                 }
+                None
+            }
+            Effect::KILL => {
+                self.die(scene, &scene.killed);
+                None
+            }
+            Effect::INJECT(dna, energy) => {
+                if let Some(old) = self.dna {
+                    scene.killed.fetch_add(1, Ordering::Relaxed);
+                    scene.ancestry.depart(old.lineage.id);
+                }
+                let tick = scene.ticks.load(Ordering::Relaxed);
+                let lineage = scene.ancestry.birth([None, None], tick);
+                scene.ancestry.arrive(&lineage);
+                self.dna = Some(Dna { lineage, ..dna });
+                self.reaction_time = Some(dna.reaction_time);
+                self.energy = energy.clamp(0.0, 1.0);
+                self.age = 0.0;
+                self.refresh_brain();
+                None
+            }
+            Effect::WALL(solid) => {
+                if solid {
+                    self.die(scene, &scene.killed);
+                    self.energy = 0.0;
+                }
+                self.wall = solid;
+                None
             }
         }
     }
 
     fn is_alive(&self) -> bool {
-        return self.dna.is_some() && self.reaction_time.is_some();
-    }
-
-    fn reach(&self, direction: Direction, distance: f64) -> Coord {
-        let sq_dist = distance * 1.41421356f64;
-        return match direction {
-            Direction::NORTH => Coord {
-                x: self.pos.x,
-                y: self.pos.y - distance,
-            },
-            Direction::NORTHEAST => Coord {
-                x: self.pos.x + sq_dist,
-                y: self.pos.y - sq_dist,
-            },
-            Direction::EAST => Coord {
-                x: self.pos.x + distance,
-                y: self.pos.y,
-            },
-            Direction::SOUTHEAST => Coord {
-                x: self.pos.x + sq_dist,
-                y: self.pos.y + sq_dist,
-            },
-            Direction::SOUTH => Coord {
-                x: self.pos.x,
-                y: self.pos.y + distance,
-            },
-            Direction::SOUTHWEST => Coord {
-                x: self.pos.x - sq_dist,
-                y: self.pos.y + sq_dist,
-            },
-            Direction::WEST => Coord {
-                x: self.pos.x - distance,
-                y: self.pos.y,
-            },
-            Direction::NORTHWEST => Coord {
-                x: self.pos.x - sq_dist,
-                y: self.pos.y - sq_dist,
-            },
-        };
+        self.dna.is_some() && self.reaction_time.is_some()
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    /// A copy of what the inspector shows about this dot.
+    pub fn inspect(&self) -> Inspection {
+        Inspection {
+            pos: self.pos,
+            dna: self.dna,
+            energy: self.energy,
+            age: self.age,
+            alive: self.is_alive(),
+            scores: self.scores,
+            wall: self.wall,
+        }
+    }
+
+    /// Feed every piece of simulation state into `state`, for comparing runs.
+    pub fn hash_state<H: Hasher>(&self, state: &mut H) {
+        self.pos.hash(state);
+        self.dna.hash(state);
+        self.energy.to_bits().hash(state);
+        self.age.to_bits().hash(state);
+        self.reaction_time.hash(state);
+        // only walls hash the flag, so worlds without any keep their digests
+        if self.wall {
+            self.wall.hash(state);
+        }
+    }
+
+    // The cell `distance` steps away in `direction`, wrapped or reflected by
+    // the scene's topology; None if it falls off a bounded world.
+    fn reach(&self, direction: Direction, distance: i32, scene: &Scene) -> Option<Coord> {
+        let (dx, dy) = direction.offset();
+        let pos = self.pos.offset(dx.checked_mul(distance)?, dy.checked_mul(distance)?)?;
+        scene.resolve(pos)
     }
 }
 
@@ -321,6 +566,7 @@ impl Dot {
 mod tests {
     use super::*;
     use crate::common::dna::Dna;
+    use crate::common::genome::MUTATION_RATE;
 
     fn unpack(p: u32) -> ([f32; 3], f32) {
         (
@@ -339,31 +585,51 @@ mod tests {
     #[test]
     fn sense_is_phenotype_render_is_genome_colour() {
         let eps = 1.0 / 255.0;
-        let (tx, _rx): (Sender<(Coord, Arc<Effect>)>, _) = flume::unbounded();
+        let rng = StdRng::seed_from_u64(0);
 
-        let dead = Dot::new(Coord { x: 1.0, y: 2.0 }, None, 0.4, tx.clone());
+        let dead = Dot::new(Coord::new(1, 2), None, 0.4, rng.clone());
         for packed in [dead.pack_sense(), dead.pack_render()] {
             let (rgb, a) = unpack(packed);
             assert!(rgb.iter().all(|c| (c - 1.0).abs() <= eps), "dead is white");
             assert!((a - (0.5 + 0.4 / 2.0)).abs() <= eps, "dead opacity ramps with energy");
         }
 
-        let dna = Dna::new([0x1234_5678_9abc_def0_u64; dna::SIZE]);
-        let alive = Dot::new(Coord { x: 0.0, y: 0.0 }, Some(dna), 0.3, tx);
+        let dna = Dna::new([0x1234_5678_9abc_def0_u64; dna::SIZE], &Genetics::default());
+        let alive = Dot::new(Coord::new(0, 0), Some(dna), 0.3, rng);
 
         let (rgb, a) = unpack(alive.pack_sense());
-        for i in 0..3 {
-            assert!((rgb[i] - dna.color[i]).abs() <= eps, "sense shows phenotype [{}]", i);
+        for (i, c) in rgb.iter().enumerate() {
+            assert!((c - dna.color[i]).abs() <= eps, "sense shows phenotype [{}]", i);
         }
         assert!((a - 1.0).abs() <= eps);
 
         let (rgb, a) = unpack(alive.pack_render());
-        for i in 0..3 {
-            assert!((rgb[i] - dna.display_color[i]).abs() <= eps, "render shows genome [{}]", i);
+        for (i, c) in rgb.iter().enumerate() {
+            assert!((c - dna.display_color[i]).abs() <= eps, "render shows genome [{}]", i);
         }
         assert!((a - 1.0).abs() <= eps);
     }
 
+    // a colonising genome is mutated at its own heritable rate
+    #[test]
+    fn colonists_mutate_at_their_own_rate() {
+        let mut config = crate::config::Config::default();
+        (config.world.width, config.world.height) = (4, 4);
+        (config.genetics.max_mutations, config.genetics.mutation_rate_max) = (0, 0.05);
+        let scene = Scene::new(Arc::new(config));
+        let genetics = &scene.config().genetics;
+        let flips = |raw| {
+            let mut seq = [0x0f0f_3c3c_a5a5_6996_u64; SIZE];
+            MUTATION_RATE.write(&mut seq, 0, raw);
+            let seed = Arc::new(Effect::SEED(Dna::new(seq, genetics), 0.1));
+            let mut dot = Dot::new(Coord::new(1, 1), None, 0.0, StdRng::seed_from_u64(5));
+            dot.apply_effect(seed, &scene);
+            dna::hamming(&dot.dna.unwrap().seq, &seq)
+        };
+        assert_eq!(flips(0), 0);
+        assert!(flips(0xff) > 10, "a 5% rate flips about 70 bits");
+    }
+
     // decide() must map output indices to the right action+direction space:
     // 0..8 DIGEST, 8..16 SEED, 16 IDLE.
     #[test]
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::bus::Bus;
+use crate::common::coord::Coord;
+use crate::common::dna::{Dna, SIZE};
+use crate::config::Genetics;
+use crate::effect::Effect;
+use crate::scene::Scene;
+
+/// The energy an injected dot starts with.
+const INJECT_ENERGY: f32 = 1.0;
+
+/// What an edit does to every cell it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tool", rename_all = "snake_case")]
+pub enum Tool {
+    /// Kill the dot; it counts as killed, not as a starvation death.
+    Kill,
+    /// Replace the cell's contents with a live dot of this genome.
+    Inject { seq: [u64; SIZE] },
+    /// Add (or, below zero, drain) energy, whatever the cell holds.
+    Energy { amount: f32 },
+    /// Raise (`solid`) or clear a wall.
+    Wall { solid: bool },
+}
+
+/// One dab of a tool: a disc of cells `radius` around `center`, at
+/// simulation time `time_ms` (see `Recorder::poll`).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Edit {
+    pub time_ms: u64,
+    pub center: Coord,
+    pub radius: i32,
+    #[serde(flatten)]
+    pub tool: Tool,
+}
+
+impl Edit {
+    /// The effects that carry out the edit, one per cell of the disc in
+    /// raster order. The disc wraps or reflects with the world's topology and
+    /// is cut off at a bounded world's edge.
+    pub fn effects(&self, scene: &Scene) -> Vec<(Coord, Arc<Effect>)> {
+        let genetics = &scene.config().genetics;
+        let effect = Arc::new(match self.tool {
+            Tool::Kill => Effect::KILL,
+            Tool::Inject { seq } => Effect::INJECT(Dna::new(seq, genetics), INJECT_ENERGY),
+            Tool::Energy { amount } => Effect::ENERGY(amount, None, None),
+            Tool::Wall { solid } => Effect::WALL(solid),
+        });
+        let r = self.radius.max(0);
+        let mut seen = HashSet::new();
+        let mut effects = Vec::new();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let Some(pos) = self.center.offset(dx, dy).and_then(|p| scene.resolve(p)) else {
+                    continue;
+                };
+                // a small wrapped world can bring the disc round onto itself
+                if seen.insert(pos) {
+                    effects.push((pos, effect.clone()));
+                }
+            }
+        }
+        effects
+    }
+}
+
+/// Applies edits by sending their effects down the bus, so they reach dots
+/// the way simulation events do, under whichever engine is running. Every
+/// applied edit can be appended to an edits file (JSON Lines), and a file
+/// recorded earlier replayed: its edits are applied as simulation time
+/// reaches them.
+pub struct Editor {
+    bus: Arc<Bus>,
+    log: Option<BufWriter<File>>,
+    replay: VecDeque<Edit>,
+}
+
+impl Editor {
+    pub fn new(bus: Arc<Bus>) -> Editor {
+        Editor {
+            bus,
+            log: None,
+            replay: VecDeque::new(),
+        }
+    }
+
+    pub fn record_to(mut self, path: &Path) -> Result<Editor, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.log = Some(BufWriter::new(file));
+        Ok(self)
+    }
+
+    pub fn replay_from(mut self, path: &Path) -> Result<Editor, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let edit: Edit = serde_json::from_str(&line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?;
+            self.replay.push_back(edit);
+        }
+        self.replay.make_contiguous().sort_by_key(|edit| edit.time_ms);
+        Ok(self)
+    }
+
+    /// Send the edit's effects and record it. A log that can't be written is
+    /// closed and the error returned, but the edit still goes through.
+    pub async fn apply(&mut self, edit: Edit, scene: &Scene) -> Result<(), String> {
+        for effect in edit.effects(scene) {
+            self.bus.send(effect).await;
+        }
+        let Some(log) = &mut self.log else {
+            return Ok(());
+        };
+        let line = serde_json::to_string(&edit).map_err(|e| e.to_string())?;
+        writeln!(log, "{}", line).and_then(|()| log.flush()).map_err(|e| {
+            self.log = None;
+            e.to_string()
+        })
+    }
+
+    /// Apply every replayed edit due by `time_ms`.
+    pub async fn poll(&mut self, scene: &Scene, time_ms: u64) -> Result<(), String> {
+        while self.replay.front().is_some_and(|edit| edit.time_ms <= time_ms) {
+            let edit = self.replay.pop_front().unwrap();
+            self.apply(edit, scene).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a genome for the inject tool, in the text format (see `Dna::format`).
+pub fn load_genome(path: &Path, genetics: &Genetics) -> Result<[u64; SIZE], String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let dna = Dna::parse(&text, genetics).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(dna.seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dots::DotFactory;
+    use crate::stepper::{Driver, Stepper};
+    use rand::prelude::*;
+
+    #[tokio::test]
+    async fn edits_reach_dots_through_the_bus() {
+        let mut config = Config::default();
+        (config.world.width, config.world.height) = (9, 9);
+        let scene = Arc::new(Scene::new(Arc::new(config)));
+        let bus = Arc::new(Bus::unbounded());
+        let factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(0));
+        for i in 0..81 {
+            let pos = Coord::from_index(i, scene.size());
+            scene.push_dot(pos, factory.build(pos, None, 0.5));
+        }
+        let mut stepper = Stepper::new(scene.clone(), bus.clone());
+
+        // a radius-1 disc is a plus sign; at a bounded corner only 3 cells remain
+        let edit = |center, radius, tool| Edit { time_ms: 0, center, radius, tool };
+        let seq = [0x0123_4567_89ab_cdef_u64; SIZE];
+        let inject = edit(Coord::new(4, 4), 1, Tool::Inject { seq });
+        assert_eq!(inject.effects(&scene).len(), 5);
+        assert_eq!(edit(Coord::new(0, 0), 1, Tool::Kill).effects(&scene).len(), 3);
+
+        let mut editor = Editor::new(bus.clone());
+        editor.apply(inject, &scene).await.unwrap();
+        stepper.step();
+        let dot = |x, y| scene.at(Coord::new(x, y)).unwrap().dot.try_lock().unwrap().inspect();
+        assert!(dot(4, 5).alive && dot(4, 5).dna.unwrap().seq == seq);
+        assert!(!dot(5, 5).alive, "outside the disc");
+        assert_eq!(scene.ancestry.len(), 5, "every injected dot founds a lineage");
+
+        let wall = edit(Coord::new(4, 3), 0, Tool::Wall { solid: true });
+        let feed = Edit { tool: Tool::Energy { amount: 0.5 }, ..wall };
+        for e in [edit(Coord::new(4, 4), 0, Tool::Kill), wall, feed] {
+            editor.apply(e, &scene).await.unwrap();
+        }
+        stepper.step();
+        assert!(dot(4, 4).dna.is_none());
+        let count = |n: &std::sync::atomic::AtomicU64| n.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!((count(&scene.deaths), count(&scene.killed)), (0, 2), "edits aren't starvation");
+        assert!(dot(4, 3).wall && dot(4, 3).energy == 0.0, "walls ignore other effects");
+
+        let path = std::env::temp_dir().join(format!("dots-edits-{}.jsonl", std::process::id()));
+        let mut recorder = Editor::new(bus.clone()).record_to(&path).unwrap();
+        let later = Edit { time_ms: 500, ..edit(Coord::new(1, 1), 2, Tool::Kill) };
+        recorder.apply(later, &scene).await.unwrap();
+        bus.drain();
+        let mut replay = Editor::new(bus.clone()).replay_from(&path).unwrap();
+        replay.poll(&scene, 499).await.unwrap();
+        assert_eq!(bus.metrics().depth, 0, "not due yet");
+        replay.poll(&scene, 500).await.unwrap();
+        assert_eq!(bus.metrics().depth, later.effects(&scene).len());
+        std::fs::remove_file(path).unwrap();
+    }
+}
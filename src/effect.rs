@@ -1,10 +1,21 @@
 use crate::common::coord::Coord;
 use crate::common::dna::Dna;
 
+// effects travel behind an Arc, so the size of the genome variant is fine
+#[allow(clippy::large_enum_variant)]
 #[derive(Copy, Clone, Debug)]
 pub enum Effect {
     ENERGY(f32, Option<[f32; 3]>, Option<Coord>),
     // genome, plus the energy the parent invested — credited to the offspring as
     // its starting energy on a successful seed, dissipated if the seed is wasted.
     SEED(Dna, f32),
+    // the rest come from the viewer's painting tools (see `edit`), not from dots:
+    // the dot dies, counted in `Scene::killed` rather than as a starvation death
+    KILL,
+    // replace whatever the cell holds with a live dot of this genome, founding
+    // a new lineage, at this energy
+    INJECT(Dna, f32),
+    // raise (true) or clear (false) a wall; a wall holds nothing, doesn't
+    // regrow and ignores every other effect
+    WALL(bool),
 }
@@ -0,0 +1,153 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+
+use crate::common::coord::Coord;
+use crate::dots::Cell;
+
+/// Where a scene keeps its cells. The grid's shape is fixed at startup and a
+/// cell, once placed, is never replaced — only the dot inside it changes.
+pub trait Grid: Send + Sync {
+    /// Place `cell` at the on-grid `pos` unless one is there already.
+    fn insert(&self, pos: Coord, cell: Arc<Cell>);
+    fn get(&self, pos: Coord) -> Option<Arc<Cell>>;
+    /// Look at a cell in place, without cloning its Arc.
+    fn peek(&self, pos: Coord, f: &mut dyn FnMut(&Cell));
+    /// Visit every cell; in raster order where the backend allows.
+    fn for_each(&self, f: &mut dyn FnMut(Coord, &Arc<Cell>));
+}
+
+/// Which `Grid` a scene uses, from `world.storage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A flat row-major array indexed by `Coord::index`.
+    #[default]
+    Dense,
+    /// A sharded concurrent hash map keyed by `Coord`.
+    Map,
+}
+
+impl Backend {
+    pub fn grid(self, size: Coord) -> Box<dyn Grid> {
+        match self {
+            Backend::Dense => Box::new(Dense::new(size)),
+            Backend::Map => Box::new(Sharded::default()),
+        }
+    }
+}
+
+/// One slot per cell, filled once at startup. Lookups are an index
+/// computation and a load; iteration is in raster order.
+pub struct Dense {
+    size: Coord,
+    cells: Vec<OnceLock<Arc<Cell>>>,
+}
+
+impl Dense {
+    pub fn new(size: Coord) -> Dense {
+        let len = size.x as usize * size.y as usize;
+        Dense {
+            size,
+            cells: (0..len).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    fn slot(&self, pos: Coord) -> Option<&Arc<Cell>> {
+        self.cells.get(pos.index(self.size)?)?.get()
+    }
+}
+
+impl Grid for Dense {
+    fn insert(&self, pos: Coord, cell: Arc<Cell>) {
+        if let Some(i) = pos.index(self.size) {
+            let _ = self.cells[i].set(cell);
+        }
+    }
+
+    fn get(&self, pos: Coord) -> Option<Arc<Cell>> {
+        self.slot(pos).cloned()
+    }
+
+    fn peek(&self, pos: Coord, f: &mut dyn FnMut(&Cell)) {
+        if let Some(cell) = self.slot(pos) {
+            f(cell);
+        }
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(Coord, &Arc<Cell>)) {
+        for (i, slot) in self.cells.iter().enumerate() {
+            if let Some(cell) = slot.get() {
+                f(Coord::from_index(i, self.size), cell);
+            }
+        }
+    }
+}
+
+/// The original backend. Reads take only a per-shard lock, but every lookup
+/// hashes its `Coord` and iteration follows the shards.
+#[derive(Default)]
+pub struct Sharded {
+    cells: DashMap<Coord, Arc<Cell>>,
+}
+
+impl Grid for Sharded {
+    fn insert(&self, pos: Coord, cell: Arc<Cell>) {
+        self.cells.entry(pos).or_insert(cell);
+    }
+
+    fn get(&self, pos: Coord) -> Option<Arc<Cell>> {
+        self.cells.get(&pos).map(|cell| cell.value().clone())
+    }
+
+    fn peek(&self, pos: Coord, f: &mut dyn FnMut(&Cell)) {
+        if let Some(cell) = self.cells.get(&pos) {
+            f(cell.value());
+        }
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(Coord, &Arc<Cell>)) {
+        for cell in self.cells.iter() {
+            f(*cell.key(), cell.value());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+    use crate::config::Config;
+    use crate::dots::DotFactory;
+    use crate::scene::Scene;
+    use rand::prelude::*;
+
+    #[test]
+    fn backends_agree() {
+        let size = Coord::new(5, 4);
+        let scene = Arc::new(Scene::new(Arc::new(Config::default())));
+        let factory = DotFactory::new(Arc::new(Bus::unbounded()), scene, StdRng::seed_from_u64(0));
+        let grids = [Backend::Dense.grid(size), Backend::Map.grid(size)];
+        for i in (0..20).rev() {
+            let pos = Coord::from_index(i, size);
+            let cell = factory.build(pos, None, i as f32 / 20.0);
+            for grid in &grids {
+                grid.insert(pos, cell.clone());
+            }
+        }
+        for grid in &grids {
+            let cell = grid.get(Coord::new(3, 2)).unwrap();
+            assert!(Arc::ptr_eq(&cell, &grid.get(Coord::new(3, 2)).unwrap()));
+            assert!(grid.get(Coord::new(5, 0)).is_none());
+            let mut seen = 0;
+            grid.for_each(&mut |_, _| seen += 1);
+            assert_eq!(seen, 20);
+        }
+        let dense = &grids[0];
+        let mut order = Vec::new();
+        dense.for_each(&mut |pos, _| order.push(pos.index(size).unwrap()));
+        assert_eq!(order, (0..20).collect::<Vec<_>>(), "dense iterates in raster order");
+        let pos = Coord::new(1, 1);
+        assert!(Arc::ptr_eq(&dense.get(pos).unwrap(), &grids[1].get(pos).unwrap()));
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::time::{sleep, Duration};
+
+use crate::capture::Capture;
+use crate::cli::Limit;
+use crate::edit::Editor;
+use crate::hud;
+use crate::scene::Scene;
+use crate::shutdown::Shutdown;
+use crate::stats::{Recorder, MUTATION_RATE};
+use crate::stepper::Driver;
+
+/// Run the already-seeded scene until `limit` is reached or a shutdown is
+/// requested, then print a summary. With `progress`, the HUD's figures go to
+/// stderr after every stats sample; `editor` applies any replayed edits as
+/// they fall due. Without a stepper the scene's tickers and propagators are
+/// live tokio tasks and this just waits; with one, this drives it step by
+/// step. No window, GL context or glyph cache is created, so this runs
+/// without a GPU. Returns the simulation time the run stopped at.
+#[allow(clippy::too_many_arguments)] // one per thing a run can be wired to
+pub async fn run(
+    scene: Arc<Scene>,
+    limit: Limit,
+    mut stepper: Option<Box<dyn Driver>>,
+    seed: u64,
+    mut recorder: Recorder,
+    progress: bool,
+    mut capture: Option<Capture>,
+    mut editor: Editor,
+    shutdown: &Shutdown,
+) -> u64 {
+    // stats follow simulation time, which the clock may run faster or slower
+    let start = Instant::now();
+    let poll = Duration::from_millis(50);
+    let now_ms = |stepper: &Option<Box<dyn Driver>>| match stepper {
+        Some(stepper) => stepper.now(),
+        None => scene.clock.now().as_millis() as u64,
+    };
+    loop {
+        let now_ms = now_ms(&stepper);
+        match recorder.poll(&scene, now_ms).await {
+            Ok(true) if progress => eprintln!("{}", hud::lines(recorder.history()).join("  ")),
+            Ok(_) => {}
+            Err(e) => eprintln!("stats: {}", e),
+        }
+        if let Err(e) = editor.poll(&scene, now_ms).await {
+            eprintln!("edits: {}", e);
+        }
+        if let Some(Err(e)) = capture.as_mut().map(|c| c.poll(&scene)) {
+            eprintln!("capture: {}", e);
+            capture = None;
+        }
+
+        let done = match limit {
+            Limit::Ticks(n) => scene.ticks.load(Ordering::Relaxed) >= n,
+            Limit::Duration(d) => start.elapsed() >= d,
+        };
+        if done || shutdown.requested() {
+            break;
+        }
+        match &mut stepper {
+            Some(stepper) => {
+                if !stepper.step() {
+                    break;
+                }
+            }
+            None => sleep(poll).await,
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let sample = recorder.sample(&scene, elapsed.as_millis() as u64).await;
+    println!("seed         {}", seed);
+    println!("elapsed      {:.2}s", elapsed.as_secs_f64());
+    println!(
+        "ticks        {} ({:.0}/s)",
+        sample.tick,
+        sample.tick as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    println!("cells        {}", sample.cells);
+    println!("population   {}", sample.population);
+    println!("empty        {}", sample.empty);
+    println!("births       {}", sample.births);
+    println!("deaths       {}", sample.deaths);
+    println!("killed       {}", sample.killed);
+    println!(
+        "energy       {:.2} total, {:.4} mean",
+        sample.total_energy, sample.mean_energy
+    );
+    println!("mean age     {:.3}", sample.mean_age);
+    println!("lineages     {}", scene.ancestry.len());
+    println!("species      {}", sample.species);
+    let rate = sample.traits[MUTATION_RATE];
+    println!("mutation     {:.5} mean per-bit rate, {:.5} std", rate.mean, rate.std);
+    println!(
+        "effects      {} delivered, {} dropped, {} queued, {:.3}ms mean latency",
+        sample.bus.delivered, sample.bus.dropped, sample.bus.depth, sample.latency_ms
+    );
+    if let Some(stepper) = &stepper {
+        println!("virtual time {}ms", stepper.now());
+    }
+    println!("digest       {:016x}", scene.digest().await);
+    now_ms(&stepper)
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+use crate::stats::{Sample, HISTORY};
+
+/// The HUD's figures from the latest sample: tick count, population, births
+/// and deaths per second of simulation time (over the last sampling
+/// interval), mean energy and species. Empty until the first sample.
+pub fn lines(history: &VecDeque<Sample>) -> Vec<String> {
+    let Some(latest) = history.back() else {
+        return Vec::new();
+    };
+    let (births, deaths) = match history.len().checked_sub(2).map(|i| &history[i]) {
+        Some(earlier) => {
+            let secs = latest.time_ms.saturating_sub(earlier.time_ms).max(1) as f64 / 1000.0;
+            (
+                (latest.births - earlier.births) as f64 / secs,
+                (latest.deaths - earlier.deaths) as f64 / secs,
+            )
+        }
+        None => (0.0, 0.0),
+    };
+    vec![
+        format!("tick        {}", latest.tick),
+        format!("population  {} / {}", latest.population, latest.cells),
+        format!("births/s    {:.1}", births),
+        format!("deaths/s    {:.1}", deaths),
+        format!("mean energy {:.3}", latest.mean_energy),
+        format!("species     {}", latest.species),
+    ]
+}
+
+/// A line graph of `values` (oldest first) in a `width` x `height` box with
+/// its origin top-left: the newest value sits on the right edge and older
+/// ones scroll off the left as the history fills, with 0 at the bottom and
+/// the largest value at the top.
+pub fn plot(values: &[f64], width: f64, height: f64) -> Vec<[f64; 2]> {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    let dx = width / (HISTORY - 1) as f64;
+    let right = (values.len().max(1) - 1) as f64;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let y = if max > 0.0 { v / max } else { 0.0 };
+            [width - (right - i as f64) * dx, height * (1.0 - y)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readout_and_graph() {
+        let mut history = VecDeque::new();
+        assert!(lines(&history).is_empty());
+        history.push_back(Sample {
+            time_ms: 1000,
+            births: 10,
+            ..Sample::default()
+        });
+        history.push_back(Sample {
+            tick: 42,
+            time_ms: 3000,
+            births: 30,
+            deaths: 5,
+            population: 7,
+            cells: 16,
+            ..Sample::default()
+        });
+        let lines = lines(&history);
+        assert_eq!(lines[0], "tick        42");
+        assert_eq!(lines[1], "population  7 / 16");
+        assert_eq!(lines[2], "births/s    10.0");
+        assert_eq!(lines[3], "deaths/s    2.5");
+
+        let points = plot(&[0.0, 5.0, 10.0], 100.0, 20.0);
+        let dx = 100.0 / (HISTORY - 1) as f64;
+        assert_eq!(points, vec![[100.0 - 2.0 * dx, 20.0], [100.0 - dx, 10.0], [100.0, 0.0]]);
+        assert_eq!(plot(&[0.0], 100.0, 20.0), vec![[100.0, 20.0]]);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::common::brain::N_OUT;
+use crate::common::coord::Coord;
+use crate::common::dna::Dna;
+
+/// Headings for the brain's outputs, in `Direction::from_index` order.
+pub const DIRECTIONS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// The cell under world pixel (`x`, `y`) when every cell is drawn `scale`
+/// pixels square from the origin (see `Camera::to_world`), or None off the
+/// world.
+pub fn pixel_to_coord(x: f64, y: f64, scale: f64, size: Coord) -> Option<Coord> {
+    let (cx, cy) = ((x / scale).floor(), (y / scale).floor());
+    if cx < 0.0 || cy < 0.0 || cx >= size.x as f64 || cy >= size.y as f64 {
+        return None;
+    }
+    Some(Coord::new(cx as i32, cy as i32))
+}
+
+/// Everything the inspector panel shows about one dot, copied out so the
+/// panel can be drawn without holding the dot's lock.
+pub struct Inspection {
+    pub pos: Coord,
+    pub dna: Option<Dna>,
+    pub energy: f32,
+    pub age: f32,
+    pub alive: bool,
+    /// The brain's output on the dot's latest decision, if it has made one.
+    pub scores: Option<[f32; N_OUT]>,
+    pub wall: bool,
+}
+
+impl Inspection {
+    /// The panel's text, one entry per line.
+    pub fn lines(&self) -> Vec<String> {
+        let state = match (&self.dna, self.alive) {
+            _ if self.wall => "wall",
+            (_, true) => "alive",
+            (Some(_), false) => "colonised, not yet fertilised",
+            (None, _) => "empty",
+        };
+        let mut lines = vec![
+            format!("({}, {}) {}", self.pos.x, self.pos.y, state),
+            format!("energy      {:.3}", self.energy),
+            format!("age         {:.3}", self.age),
+        ];
+        if let Some(dna) = &self.dna {
+            let rgb = |c: [f32; 3]| format!("{:.2} {:.2} {:.2}", c[0], c[1], c[2]);
+            lines.push(format!("color       {}", rgb(dna.color)));
+            lines.push(format!("digest mask {}", rgb(dna.digest_mask)));
+            lines.push(format!("reaction    {}ms", dna.reaction_time.as_millis()));
+            lines.push(format!("seed invest {:.3}", dna.seed_invest));
+            lines.push(format!("mutation    {:.5}", dna.mutation_rate));
+        }
+        if let Some(scores) = &self.scores {
+            let row = |name: &str, scores: &[f32]| {
+                scores.iter().fold(format!("{:<6}", name), |row, s| row + &format!("{:>6.2}", s))
+            };
+            lines.push(DIRECTIONS.iter().fold(" ".repeat(6), |row, d| row + &format!("{:>6}", d)));
+            lines.push(row("digest", &scores[..8]));
+            lines.push(row("seed", &scores[8..16]));
+            lines.push(row("idle", &scores[16..]));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_map_to_cells() {
+        let size = Coord::new(4, 3);
+        assert_eq!(pixel_to_coord(0.0, 0.0, 5.0, size), Some(Coord::new(0, 0)));
+        assert_eq!(pixel_to_coord(4.99, 4.99, 5.0, size), Some(Coord::new(0, 0)));
+        assert_eq!(pixel_to_coord(5.0, 14.9, 5.0, size), Some(Coord::new(1, 2)));
+        assert_eq!(pixel_to_coord(19.9, 0.0, 5.0, size), Some(Coord::new(3, 0)));
+        assert_eq!(pixel_to_coord(20.0, 0.0, 5.0, size), None);
+        assert_eq!(pixel_to_coord(0.0, 15.0, 5.0, size), None);
+        assert_eq!(pixel_to_coord(-0.1, 3.0, 5.0, size), None);
+        assert_eq!(pixel_to_coord(7.0, 7.0, 1.0, size), None);
+
+        let empty = Inspection {
+            pos: Coord::new(1, 2),
+            dna: None,
+            energy: 0.5,
+            age: 0.0,
+            alive: false,
+            scores: None,
+            wall: false,
+        };
+        assert_eq!(empty.lines()[0], "(1, 2) empty");
+        assert_eq!(empty.lines().len(), 3);
+    }
+}
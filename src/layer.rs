@@ -0,0 +1,214 @@
+use std::sync::atomic::Ordering;
+
+use crate::action::Action;
+use crate::config::Config;
+use crate::dots::Cell;
+
+/// What the viewer colours cells by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layer {
+    /// The colour of the whole genome, so relatives look alike.
+    #[default]
+    Genome,
+    /// The colour neighbours sense.
+    Phenotype,
+    Energy,
+    Age,
+    ReactionTime,
+    SeedInvest,
+    /// A colour per species, as last classified (see `Cell::species`).
+    Species,
+    /// The action the dot's latest decision chose.
+    Action,
+}
+
+pub const LAYERS: [Layer; 8] = [
+    Layer::Genome,
+    Layer::Phenotype,
+    Layer::Energy,
+    Layer::Age,
+    Layer::ReactionTime,
+    Layer::SeedInvest,
+    Layer::Species,
+    Layer::Action,
+];
+
+impl Layer {
+    /// The layer after this one, wrapping around.
+    pub fn next(self) -> Layer {
+        let i = LAYERS.iter().position(|&l| l == self).unwrap();
+        LAYERS[(i + 1) % LAYERS.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Genome => "genome",
+            Layer::Phenotype => "phenotype",
+            Layer::Energy => "energy",
+            Layer::Age => "age",
+            Layer::ReactionTime => "reaction time",
+            Layer::SeedInvest => "seed invest",
+            Layer::Species => "species",
+            Layer::Action => "last action",
+        }
+    }
+
+    /// A cell's colour and opacity on this layer, read from its atomic
+    /// snapshots without locking the dot. Heatmaps run from dark blue (low)
+    /// to pale yellow (high); cells without a genome stay dark on every
+    /// layer but energy.
+    pub fn paint(self, cell: &Cell, config: &Config) -> ([f32; 3], f32) {
+        let vitals = Vitals::unpack(cell.vitals.load(Ordering::Relaxed));
+        let genetics = &config.genetics;
+        let dark = ([0.0; 3], 0.0);
+        match self {
+            Layer::Genome => unpack_rgba(cell.render.load(Ordering::Relaxed)),
+            Layer::Phenotype => unpack_rgba(cell.sense.load(Ordering::Relaxed)),
+            Layer::Energy => (heat(vitals.energy), 1.0),
+            _ if !vitals.genome => dark,
+            Layer::Age => (heat(vitals.age), 1.0),
+            Layer::ReactionTime => {
+                let ms = vitals.reaction_ms as f32 - genetics.reaction_floor_ms as f32;
+                (heat(ms / u8::MAX as f32), 1.0)
+            }
+            Layer::SeedInvest => (heat(vitals.seed_invest / genetics.seed_invest_max), 1.0),
+            Layer::Species => match cell.species.load(Ordering::Relaxed) {
+                0 => dark,
+                id => (id_color(id), 1.0),
+            },
+            Layer::Action => match vitals.action {
+                Some(Action::DIGEST) => ([0.9, 0.2, 0.2], 1.0),
+                Some(Action::SEED) => ([0.2, 0.9, 0.3], 1.0),
+                Some(Action::IDLE) => ([0.3, 0.4, 0.9], 1.0),
+                None => ([0.5; 3], 0.3),
+            },
+        }
+    }
+}
+
+/// What the layers need to know about a dot beyond its colours, quantised to
+/// fit one `AtomicU64` so a cell can publish it in a single store.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vitals {
+    /// 0 to 1, to 16 bits.
+    pub energy: f32,
+    /// Clamped to 0 to 1, to 12 bits; older dots have starved anyway.
+    pub age: f32,
+    /// The genome's reaction time, up to 4095ms.
+    pub reaction_ms: u16,
+    /// 0 to 1, to 16 bits.
+    pub seed_invest: f32,
+    pub action: Option<Action>,
+    pub genome: bool,
+}
+
+impl Vitals {
+    pub fn pack(&self) -> u64 {
+        let q = |f: f32, bits: u32| (f.clamp(0.0, 1.0) * ((1 << bits) - 1) as f32).round() as u64;
+        let action = match self.action {
+            None => 0,
+            Some(Action::DIGEST) => 1,
+            Some(Action::SEED) => 2,
+            Some(Action::IDLE) => 3,
+        };
+        q(self.energy, 16)
+            | q(self.age, 12) << 16
+            | (self.reaction_ms.min(0xfff) as u64) << 28
+            | q(self.seed_invest, 16) << 40
+            | action << 56
+            | (self.genome as u64) << 58
+    }
+
+    pub fn unpack(v: u64) -> Vitals {
+        let f = |shift: u32, bits: u32| {
+            let max = (1 << bits) - 1;
+            ((v >> shift) & max) as f32 / max as f32
+        };
+        Vitals {
+            energy: f(0, 16),
+            age: f(16, 12),
+            reaction_ms: ((v >> 28) & 0xfff) as u16,
+            seed_invest: f(40, 16),
+            action: match (v >> 56) & 0b11 {
+                0 => None,
+                1 => Some(Action::DIGEST),
+                2 => Some(Action::SEED),
+                _ => Some(Action::IDLE),
+            },
+            genome: (v >> 58) & 1 == 1,
+        }
+    }
+}
+
+/// An RGBA8 snapshot (see `Cell`) as colour and opacity.
+pub fn unpack_rgba(packed: u32) -> ([f32; 3], f32) {
+    let rgb = [
+        ((packed >> 24) & 0xff) as f32 / 255.0,
+        ((packed >> 16) & 0xff) as f32 / 255.0,
+        ((packed >> 8) & 0xff) as f32 / 255.0,
+    ];
+    (rgb, (packed & 0xff) as f32 / 255.0)
+}
+
+// Dark blue through red to pale yellow as `t` goes from 0 to 1.
+fn heat(t: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 3] = [[0.05, 0.05, 0.35], [0.85, 0.2, 0.2], [1.0, 0.95, 0.6]];
+    let t = t.clamp(0.0, 1.0) * 2.0;
+    let (a, b) = if t < 1.0 { (STOPS[0], STOPS[1]) } else { (STOPS[1], STOPS[2]) };
+    let t = if t < 1.0 { t } else { t - 1.0 };
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+// A bright colour that stays the same for an id: the hue steps round the
+// wheel by the golden angle, so consecutive ids look far apart.
+fn id_color(id: u64) -> [f32; 3] {
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    match hue as u32 {
+        0 => [1.0, x, 0.2],
+        1 => [x, 1.0, 0.2],
+        2 => [0.2, 1.0, x],
+        3 => [0.2, x, 1.0],
+        4 => [x, 0.2, 1.0],
+        _ => [1.0, 0.2, x],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vitals_survive_packing() {
+        let vitals = Vitals {
+            energy: 0.75,
+            age: 0.25,
+            reaction_ms: 143,
+            seed_invest: 0.125,
+            action: Some(Action::SEED),
+            genome: true,
+        };
+        let back = Vitals::unpack(vitals.pack());
+        assert!((back.energy - 0.75).abs() < 1e-4);
+        assert!((back.age - 0.25).abs() < 1e-3);
+        assert!((back.seed_invest - 0.125).abs() < 1e-4);
+        assert_eq!((back.reaction_ms, back.action, back.genome), (143, Some(Action::SEED), true));
+        assert_eq!(Vitals::unpack(Vitals::default().pack()), Vitals::default());
+    }
+
+    #[test]
+    fn heat_runs_from_cold_to_hot() {
+        assert_eq!(heat(0.0), [0.05, 0.05, 0.35]);
+        assert_eq!(heat(1.0), [1.0, 0.95, 0.6]);
+    }
+
+    #[test]
+    fn next_cycles_through_every_layer() {
+        let mut layer = Layer::default();
+        for expected in LAYERS.iter().cycle().skip(1).take(LAYERS.len()) {
+            layer = layer.next();
+            assert_eq!(layer, *expected);
+        }
+        assert_eq!(layer, Layer::Genome);
+    }
+}
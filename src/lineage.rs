@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// Where a genome came from. Carried by every `Dna`; `id` 0 means the genome
+/// was never registered with an `Ancestry`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub id: u64,
+    /// The resident genome that was fertilised first, then the pollen's.
+    /// Both `None` for a founding genome.
+    pub parents: [Option<u64>; 2],
+    /// Scene tick count when the genome was created.
+    pub born: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub parents: [Option<u64>; 2],
+    pub born: u64,
+    /// Cells currently carrying this genome.
+    pub living: u32,
+    /// Child nodes still in the graph, as a first or second parent.
+    pub children: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Graph {
+    next_id: u64,
+    nodes: HashMap<u64, Node>,
+}
+
+/// The ancestry graph of every genome that is alive or has living
+/// descendants. A genome whose carriers have all died and which has no
+/// children left is pruned, and its parents are checked in turn, so extinct
+/// branches fall away as the run goes.
+#[derive(Default)]
+pub struct Ancestry {
+    graph: Mutex<Graph>,
+}
+
+impl Ancestry {
+    /// Register a new genome with no carriers yet.
+    pub fn birth(&self, parents: [Option<u64>; 2], born: u64) -> Lineage {
+        let mut g = self.graph.lock().unwrap();
+        g.next_id += 1;
+        let id = g.next_id;
+        for p in parents.iter().flatten() {
+            if let Some(node) = g.nodes.get_mut(p) {
+                node.children += 1;
+            }
+        }
+        g.nodes.insert(
+            id,
+            Node {
+                parents,
+                born,
+                living: 0,
+                children: 0,
+            },
+        );
+        Lineage { id, parents, born }
+    }
+
+    /// A cell now carries `lineage`. A genome can be pruned while a seed of
+    /// it is still in flight; it is re-inserted from the lineage it carries.
+    pub fn arrive(&self, lineage: &Lineage) {
+        let mut g = self.graph.lock().unwrap();
+        if !g.nodes.contains_key(&lineage.id) {
+            for p in lineage.parents.iter().flatten() {
+                if let Some(node) = g.nodes.get_mut(p) {
+                    node.children += 1;
+                }
+            }
+            let node = Node {
+                parents: lineage.parents,
+                born: lineage.born,
+                living: 0,
+                children: 0,
+            };
+            g.nodes.insert(lineage.id, node);
+        }
+        g.nodes.get_mut(&lineage.id).unwrap().living += 1;
+    }
+
+    /// A cell carrying `id` died or was overwritten.
+    pub fn depart(&self, id: u64) {
+        let mut g = self.graph.lock().unwrap();
+        if let Some(node) = g.nodes.get_mut(&id) {
+            node.living = node.living.saturating_sub(1);
+        }
+        g.prune_from(id);
+    }
+
+    /// Prune every extinct leaf, e.g. founders whose seeds never landed.
+    pub fn prune(&self) {
+        let mut g = self.graph.lock().unwrap();
+        let ids: Vec<u64> = g.nodes.keys().copied().collect();
+        for id in ids {
+            g.prune_from(id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.lock().unwrap().nodes.len()
+    }
+
+    pub fn save(&self) -> Graph {
+        let g = self.graph.lock().unwrap();
+        Graph {
+            next_id: g.next_id,
+            nodes: g.nodes.clone(),
+        }
+    }
+
+    pub fn restore(&self, graph: Graph) {
+        *self.graph.lock().unwrap() = graph;
+    }
+
+    /// The graph as a Newick tree. Newick can't express two parents, so each
+    /// genome hangs under its first (resident) parent; founders hang under
+    /// one unnamed root. Branch lengths are in ticks.
+    pub fn newick(&self) -> String {
+        let g = self.graph.lock().unwrap();
+        let kids = g.tree_children();
+        let mut out = String::new();
+        // iterative post-order: deep lineages would overflow a recursive walk
+        let roots = kids.get(&0).cloned().unwrap_or_default();
+        let mut stack: Vec<Visit> = Vec::new();
+        push_children(&mut stack, &roots);
+        out.push('(');
+        while let Some(v) = stack.pop() {
+            match v {
+                Visit::Comma => out.push(','),
+                Visit::Open(id) => {
+                    stack.push(Visit::Close(id));
+                    if let Some(children) = kids.get(&id) {
+                        out.push('(');
+                        push_children(&mut stack, children);
+                    }
+                }
+                Visit::Close(id) => {
+                    if kids.contains_key(&id) {
+                        out.push(')');
+                    }
+                    let node = &g.nodes[&id];
+                    let parent_born = node.parents[0]
+                        .and_then(|p| g.nodes.get(&p))
+                        .map_or(0, |p| p.born);
+                    let _ = write!(out, "g{}:{}", id, node.born.saturating_sub(parent_born));
+                }
+            }
+        }
+        out.push_str(");");
+        out
+    }
+
+    /// The full graph as GraphML, with an edge from each parent.
+    pub fn graphml(&self) -> String {
+        let g = self.graph.lock().unwrap();
+        let mut ids: Vec<&u64> = g.nodes.keys().collect();
+        ids.sort();
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"born\" for=\"node\" attr.name=\"born\" attr.type=\"long\"/>\n",
+            "  <key id=\"living\" for=\"node\" attr.name=\"living\" attr.type=\"int\"/>\n",
+            "  <graph id=\"ancestry\" edgedefault=\"directed\">\n",
+        ));
+        for id in &ids {
+            let node = &g.nodes[id];
+            let _ = writeln!(
+                out,
+                "    <node id=\"g{}\"><data key=\"born\">{}</data>\
+                 <data key=\"living\">{}</data></node>",
+                id, node.born, node.living
+            );
+        }
+        for id in &ids {
+            for p in g.nodes[id].parents.iter().flatten() {
+                if g.nodes.contains_key(p) {
+                    let _ = writeln!(out, "    <edge source=\"g{}\" target=\"g{}\"/>", p, id);
+                }
+            }
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+enum Visit {
+    Open(u64),
+    Close(u64),
+    Comma,
+}
+
+fn push_children(stack: &mut Vec<Visit>, children: &[u64]) {
+    // reversed so they pop in ascending order
+    for (i, &c) in children.iter().enumerate().rev() {
+        stack.push(Visit::Open(c));
+        if i > 0 {
+            stack.push(Visit::Comma);
+        }
+    }
+}
+
+impl Graph {
+    fn prune_from(&mut self, id: u64) {
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let extinct = self.nodes.get(&id).is_some_and(|n| n.living == 0 && n.children == 0);
+            if !extinct {
+                continue;
+            }
+            let node = self.nodes.remove(&id).unwrap();
+            for p in node.parents.iter().flatten() {
+                if let Some(parent) = self.nodes.get_mut(p) {
+                    parent.children -= 1;
+                    pending.push(*p);
+                }
+            }
+        }
+    }
+
+    // Tree view for Newick: children keyed by first parent still in the
+    // graph, or 0 for roots; sorted for a stable output.
+    fn tree_children(&self) -> HashMap<u64, Vec<u64>> {
+        let mut kids: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (&id, node) in &self.nodes {
+            let parent = node.parents[0].filter(|p| self.nodes.contains_key(p)).unwrap_or(0);
+            kids.entry(parent).or_default().push(id);
+        }
+        for children in kids.values_mut() {
+            children.sort();
+        }
+        kids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extinct_branches_are_pruned() {
+        let a = Ancestry::default();
+        let x = a.birth([None, None], 0);
+        let y = a.birth([None, None], 0);
+        a.arrive(&x);
+        a.arrive(&y);
+        let child = a.birth([Some(x.id), Some(y.id)], 5);
+        a.arrive(&child);
+        assert_eq!(a.len(), 3);
+
+        // parents die but the child lives on: nothing is pruned
+        a.depart(x.id);
+        a.depart(y.id);
+        assert_eq!(a.len(), 3);
+
+        // the last carrier dies: the whole branch falls away
+        a.depart(child.id);
+        assert_eq!(a.len(), 0);
+    }
+
+    #[test]
+    fn exports() {
+        let a = Ancestry::default();
+        let x = a.birth([None, None], 0);
+        let y = a.birth([None, None], 2);
+        let child = a.birth([Some(x.id), Some(y.id)], 5);
+        for l in [&x, &y, &child] {
+            a.arrive(l);
+        }
+        assert_eq!(a.newick(), "((g3:5)g1:0,g2:2);");
+
+        let xml = a.graphml();
+        assert_eq!(xml.matches("<node ").count(), 3);
+        assert!(xml.contains("<edge source=\"g1\" target=\"g3\"/>"));
+        assert!(xml.contains("<edge source=\"g2\" target=\"g3\"/>"));
+    }
+}
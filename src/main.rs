@@ -1,71 +1,281 @@
+#![allow(clippy::upper_case_acronyms)] // enum variants are SHOUTED by convention here
+
 mod action;
 mod app;
+mod bench;
+mod bus;
+mod camera;
+mod capture;
+mod cli;
+mod clock;
 mod common;
+mod config;
+mod decode;
 mod dots;
+mod edit;
 mod effect;
+mod grid;
+mod headless;
+mod hud;
+mod inspector;
+mod layer;
+mod lineage;
+mod parallel;
 mod scene;
+mod shutdown;
+mod snapshot;
+mod species;
+mod stats;
+mod stepper;
 
 use crate::app::App;
-use crate::common::coord::Coord;
-use crate::effect::Effect;
+use crate::bus::Bus;
+use crate::capture::Capture;
+use crate::cli::{Args, Engine, GeneFormat, USAGE};
+use crate::common::dna::SIZE;
+use crate::common::genome;
+use crate::config::Config;
+use crate::edit::Editor;
+use crate::parallel::Parallel;
 use crate::scene::Scene;
+use crate::shutdown::{Shutdown, Token};
+use crate::snapshot::Snapshot;
+use crate::stats::Recorder;
+use crate::stepper::{Driver, Stepper};
 
-use common::dna::Dna;
-use common::dna::SIZE;
 use dots::DotFactory;
-use flume::{unbounded, Receiver, Sender};
 use futures::lock::Mutex;
 use glutin_window::GlutinWindow;
 use opengl_graphics::{GlGraphics, GlyphCache, OpenGL, TextureSettings};
 use piston::event_loop::*;
 use piston::input::*;
-use piston::window::WindowSettings;
+use piston::window::{Window, WindowSettings};
 use rand::prelude::*;
 use std::sync::Arc;
 use tokio::task::{spawn, JoinHandle};
 
 #[tokio::main]
 async fn main() {
-    let scene_size = Coord { x: 320.0, y: 180.0 };
-    let scale = 4;
-    let (tx, rx): (Sender<(Coord, Arc<Effect>)>, Receiver<(Coord, Arc<Effect>)>) = unbounded();
-
-    let scene = Arc::new(Scene::new(scene_size, scale));
-    let dot_factory = DotFactory::new(tx.clone(), scene.clone());
-
-    for x in 0..scene_size.x as u16 {
-        for y in 0..scene_size.y as u16 {
-            let pos = Coord {
-                x: x.into(),
-                y: y.into(),
-            };
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(format) = args.genes {
+        match format {
+            GeneFormat::Table => println!("{}", genome::table()),
+            GeneFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&genome::GENES).unwrap())
+            }
+        }
+        return;
+    }
+
+    let mut config = match &args.config {
+        Some(path) => Config::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    if args.bench {
+        bench::run(&config);
+        return;
+    }
+    if let Some(text) = &args.decode {
+        if let Err(e) = decode::run(text, &config) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let snapshot = args.load.as_ref().map(|path| {
+        Snapshot::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    // a resumed world keeps the size it was saved with
+    if let Some(snapshot) = &snapshot {
+        config.world.width = snapshot.size.x as u32;
+        config.world.height = snapshot.size.y as u32;
+    }
+    let config = Arc::new(config);
+
+    // backpressure only matters where effects race through the bus; the
+    // synchronous engines empty it themselves every step
+    let capacity = (args.engine == Engine::Async).then_some(config.bus.capacity);
+    let bus = Arc::new(Bus::new(capacity, config.bus.policy));
 
-            scene.push_dot(pos, dot_factory.create(pos, None, 1.0).await);
-            if x % 9 == 4 && y % 9 == 4 {
-                for _ in 0..2 {
-                    tx.send_async((
-                        Coord {
-                            x: x.into(),
-                            y: y.into(),
-                        },
-                        Arc::new(Effect::SEED(Dna::new(thread_rng().gen::<[u64; SIZE]>()), 0.0)),
-                    ))
-                    .await
-                    .unwrap();
+    // every random choice in a run descends from this one seed
+    let seed = args.seed.unwrap_or_else(|| thread_rng().gen());
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // the first Ctrl-C ends the run cleanly, a second one at once
+    let shutdown = Arc::new(Shutdown::default());
+    spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if shutdown.requested() {
+                    std::process::exit(130);
                 }
+                eprintln!("stopping; press Ctrl-C again to quit at once");
+                shutdown.request();
             }
         }
+    });
+
+    let scene = Arc::new(Scene::new(config.clone()));
+    if let Some(speed) = args.speed {
+        scene.clock.set_scale(speed);
+    }
+    let dot_factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(rng.gen()))
+        .stopped_by(shutdown.tickers());
+    let tick = args.engine == Engine::Async;
+    // propagators first, so seeding a world can't fill a bounded bus
+    if tick {
+        for _ in 0..config.engine.propagators {
+            shutdown.track(spawn_propagator(bus.clone(), scene.clone(), shutdown.propagators()));
+        }
+    }
+    // a loaded world carries on from the time it was saved at
+    let start_ms = snapshot.as_ref().map_or(0, |s| s.time_ms);
+    match snapshot {
+        Some(snapshot) => snapshot.restore(&scene, &dot_factory, &bus, tick).await,
+        None => dot_factory.populate(&mut rng, tick).await,
+    }
+
+    let stepper: Option<Box<dyn Driver>> = match args.engine {
+        Engine::Async => None,
+        Engine::Step => {
+            let stepper = Stepper::new(scene.clone(), bus.clone()).starting_at(start_ms);
+            Some(Box::new(stepper))
+        }
+        Engine::Parallel => {
+            let step_ms = config.engine.step_ms;
+            let parallel = Parallel::new(scene.clone(), bus.clone(), step_ms);
+            Some(Box::new(parallel.starting_at(start_ms)))
+        }
+    };
+
+    // always sampling, for the HUD or headless progress, if not for files
+    let (every_ms, threshold) = (config.stats.every_ms, config.species.threshold);
+    let mut recorder = Ok(Recorder::new(bus.clone(), every_ms, threshold));
+    if let Some(path) = &args.stats {
+        recorder = recorder.and_then(|r| r.stats_to(path));
+    }
+    if let Some(path) = &args.species {
+        recorder = recorder.and_then(|r| r.species_to(path));
+    }
+    let recorder = recorder.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let mut capture = None;
+    if args.frames.is_some() || args.gif.is_some() {
+        let mut c = Ok(Capture::new(config.capture.every_ticks, config.capture.frame_delay_ms));
+        if let Some(dir) = &args.frames {
+            c = c.and_then(|c| c.frames_to(dir, &scene));
+        }
+        if let Some(path) = &args.gif {
+            c = c.and_then(|c| c.gif_to(path, &scene));
+        }
+        capture = Some(c.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }));
+    }
+
+    let mut editor = Ok(Editor::new(bus.clone()));
+    if let Some(path) = &args.edits {
+        editor = editor.and_then(|e| e.record_to(path));
+    }
+    if let Some(path) = &args.replay {
+        editor = editor.and_then(|e| e.replay_from(path));
     }
+    let genome = args.genome.as_deref().map(|path| edit::load_genome(path, &config.genetics));
+    let genome = genome.transpose();
+    let (editor, genome) = editor.and_then(|e| Ok((e, genome?))).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
-    let _ = spawn_propagator(rx.clone(), scene.clone());
-    let _ = spawn_propagator(rx.clone(), scene.clone());
-    let _ = spawn_propagator(rx.clone(), scene.clone());
-    let _ = spawn_propagator(rx.clone(), scene.clone());
+    // where simulation time stopped, for a snapshot to carry on from
+    let time_ms = match args.headless {
+        Some(limit) => {
+            let (scene, progress) = (scene.clone(), args.progress);
+            headless::run(
+                scene, limit, stepper, seed, recorder, progress, capture, editor, &shutdown,
+            )
+            .await
+        }
+        None => {
+            // random inject genomes, drawn after the world's so they don't shift it
+            let rng = StdRng::seed_from_u64(rng.gen());
+            let scene = scene.clone();
+            run_window(scene, stepper, recorder, capture, editor, genome, rng, &shutdown).await
+        }
+    };
 
+    // quiesce the world before anything reads it for the last time
+    let report = shutdown.run(&scene, &bus).await;
+    if tick {
+        println!(
+            "shutdown     {} tickers and {} propagators stopped in {:.0}ms, \
+             {} effects drained, {} queued",
+            report.tickers,
+            report.propagators,
+            report.elapsed.as_secs_f64() * 1000.0,
+            report.drained,
+            report.queued
+        );
+    }
+
+    if let Some(path) = &args.save {
+        if let Err(e) = Snapshot::capture(&scene, &bus, time_ms).await.save(path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &args.lineage {
+        scene.ancestry.prune();
+        let tree = match path.extension().and_then(|e| e.to_str()) {
+            Some("graphml") => scene.ancestry.graphml(),
+            _ => scene.ancestry.newick(),
+        };
+        if let Err(e) = std::fs::write(path, tree) {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// The largest window opened at startup, in pixels.
+const MAX_WINDOW: [f64; 2] = [1600.0, 900.0];
+
+#[allow(clippy::too_many_arguments)] // one per thing the viewer can be wired to
+async fn run_window(
+    scene: Arc<Scene>,
+    stepper: Option<Box<dyn Driver>>,
+    recorder: Recorder,
+    capture: Option<Capture>,
+    editor: Editor,
+    genome: Option<[u64; SIZE]>,
+    rng: StdRng,
+    shutdown: &Shutdown,
+) -> u64 {
+    let config = scene.config().clone();
+    // the whole world at 1:1 if the screen allows; the camera handles the rest
+    let [width, height] = scene.world_pixels();
     let open_gl_version = OpenGL::V3_2;
     let window: GlutinWindow = WindowSettings::new(
         "Dots",
-        [scene_size.x * scale as f64, scene_size.y * scale as f64],
+        [width.min(MAX_WINDOW[0]), height.min(MAX_WINDOW[1])],
     )
     .exit_on_esc(true)
     .graphics_api(open_gl_version)
@@ -77,30 +287,66 @@ async fn main() {
         GlyphCache::new("assets/UbuntuMono-Regular.ttf", (), TextureSettings::new()).unwrap(),
     );
 
-    let mut app = App::new(scene.clone(), window, gl, glyph_cache);
+    let mut app =
+        App::new(scene, window, gl, glyph_cache, stepper, recorder, capture, editor, rng);
+    if let Some(seq) = genome {
+        app.inject_with(seq, "file");
+    }
     let mut es = EventSettings::new();
-    es.set_ups(12);
-    es.set_max_fps(30);
+    es.set_ups(config.engine.ups);
+    es.set_max_fps(config.engine.max_fps);
     let mut events = Events::new(es);
     while let Some(e) = events.next(&mut app.window) {
+        if shutdown.requested() {
+            app.window.set_should_close(true);
+        }
         if let Some(r) = e.render_args() {
             app.render(&r).await;
         }
+        if let Some(pos) = e.mouse_cursor_args() {
+            app.cursor(pos);
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(key)) => app.key(key).await,
+            Some(Button::Mouse(button)) => app.press(button),
+            _ => {}
+        }
+        if let Some(Button::Mouse(button)) = e.release_args() {
+            app.release(button);
+        }
+        if let Some([_, notches]) = e.mouse_scroll_args() {
+            app.scroll(notches);
+        }
         if let Some(u) = e.update_args() {
             app.update(&u).await;
         }
     }
+    app.now_ms()
 }
 
-fn spawn_propagator(rx: Receiver<(Coord, Arc<Effect>)>, scene: Arc<Scene>) -> JoinHandle<()> {
-    return spawn(async move {
-        while let Ok((pos, effect)) = rx.recv_async().await {
-            if let Some(cell) = scene.at(pos) {
+// Apply effects from the bus until `stop` is cancelled and the bus is empty.
+fn spawn_propagator(bus: Arc<Bus>, scene: Arc<Scene>, mut stop: Token) -> JoinHandle<()> {
+    spawn(async move {
+        loop {
+            // biased: keep draining while anything is queued
+            let mut next = tokio::select! {
+                biased;
+                delivery = bus.recv() => Some(delivery),
+                () = stop.cancelled() => break,
+            };
+            while let Some((pos, effect)) = next.take() {
+                let Some(cell) = scene.at(pos) else {
+                    continue;
+                };
                 let mut dot = cell.dot.lock().await;
-                dot.apply_effect(effect).await;
+                let reply = dot.apply_effect(effect, &scene);
                 // refresh the lock-free snapshots after mutating
                 cell.refresh_snapshots(&dot);
+                drop(dot);
+                // waiting for room on the bus this task drains could deadlock,
+                // so a reply that doesn't fit is applied here instead
+                next = reply.and_then(|reply| bus.offer(reply));
             }
         }
-    });
+    })
 }
@@ -0,0 +1,155 @@
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::bus::Bus;
+use crate::common::coord::Coord;
+use crate::dots::Cell;
+use crate::effect::Effect;
+use crate::scene::Scene;
+use crate::stepper::Driver;
+
+/// Synchronous, data-parallel engine. Time advances in fixed steps of
+/// `step_ms` virtual milliseconds, and a dot ticks on the first step at or
+/// after its reaction time has passed. Each step runs in three phases:
+///
+/// 1. every due dot senses and decides in parallel, against the snapshots
+///    its neighbours had at the start of the step;
+/// 2. the ticked cells publish their new snapshots;
+/// 3. the emitted effects are applied in order of target cell, then of
+///    emitting cell, and so are any replies, until none are left.
+///
+/// No phase reads what another thread of the same phase writes, so the
+/// result is the same whatever the thread count and matches from seed to seed.
+pub struct Parallel {
+    scene: Arc<Scene>,
+    // effects sent from outside the engine (e.g. the initial seeding)
+    bus: Arc<Bus>,
+    cells: Vec<Arc<Cell>>,
+    step_ms: u64,
+    // due step time -> indices into `cells`
+    due: BTreeMap<u64, Vec<usize>>,
+    now: u64,
+}
+
+impl Parallel {
+    /// Take over a populated scene whose cells were built without tickers.
+    pub fn new(scene: Arc<Scene>, bus: Arc<Bus>, step_ms: u64) -> Parallel {
+        let cells: Vec<Arc<Cell>> = scene.cells().into_iter().map(|(_, c)| c).collect();
+        let due = BTreeMap::from([(0, (0..cells.len()).collect())]);
+        Parallel {
+            scene,
+            bus,
+            cells,
+            step_ms,
+            due,
+            now: 0,
+        }
+    }
+
+    /// Start from virtual time `ms` rather than 0; every dot is first due then.
+    pub fn starting_at(mut self, ms: u64) -> Parallel {
+        self.due = BTreeMap::from([(ms, (0..self.cells.len()).collect())]);
+        self.now = ms;
+        self
+    }
+
+    fn resolve(&self, mut batch: Vec<(Coord, Arc<Effect>)>) {
+        let size = self.scene.size();
+        while !batch.is_empty() {
+            // stable: effects on one cell keep the raster order of their senders
+            let mut targets: Vec<_> = batch
+                .into_iter()
+                .filter_map(|(pos, effect)| Some((self.scene.resolve(pos)?, effect)))
+                .collect();
+            targets.sort_by_key(|(pos, _)| pos.index(size));
+            batch = Vec::new();
+            for (pos, effect) in targets {
+                if let Some(cell) = self.scene.at(pos) {
+                    let mut dot = cell.dot.try_lock().expect("the engine owns every dot");
+                    batch.extend(dot.apply_effect(effect, &self.scene));
+                    cell.refresh_snapshots(&dot);
+                }
+            }
+        }
+    }
+}
+
+impl Driver for Parallel {
+    fn now(&self) -> u64 {
+        self.now
+    }
+
+    fn step(&mut self) -> bool {
+        let Some((now, mut due)) = self.due.pop_first() else {
+            return false;
+        };
+        self.now = now;
+        due.sort_unstable();
+
+        let scene = &self.scene;
+        let ticked: Vec<_> = due
+            .par_iter()
+            .map(|&i| {
+                let mut dot = self.cells[i].dot.try_lock().expect("the engine owns every dot");
+                let effect = dot.tick(scene);
+                (effect, dot.next_tick_in().as_millis() as u64)
+            })
+            .collect();
+        due.par_iter().for_each(|&i| {
+            let cell = &self.cells[i];
+            cell.refresh_snapshots(&cell.dot.try_lock().expect("the engine owns every dot"));
+        });
+
+        let mut batch: Vec<(Coord, Arc<Effect>)> = self.bus.drain();
+        for (&i, (effect, wait)) in due.iter().zip(ticked) {
+            batch.extend(effect);
+            // round up to the next step boundary, at least one step on
+            let next = now + wait.max(1).div_ceil(self.step_ms) * self.step_ms;
+            self.due.entry(next).or_default().push(i);
+        }
+        self.resolve(batch);
+        true
+    }
+
+    fn advance(&mut self, ms: u64) {
+        let until = self.now + ms;
+        while self.due.first_key_value().is_some_and(|(t, _)| *t <= until) {
+            self.step();
+        }
+        self.now = until;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dots::DotFactory;
+    use rand::prelude::*;
+
+    async fn run(seed: u64, steps: usize, threads: usize) -> u64 {
+        let bus = Arc::new(Bus::unbounded());
+        let mut config = Config::default();
+        (config.world.width, config.world.height) = (27, 18);
+        let scene = Arc::new(Scene::new(Arc::new(config)));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(rng.gen()));
+        factory.populate(&mut rng, false).await;
+        let mut engine = Parallel::new(scene.clone(), bus, 16);
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            for _ in 0..steps {
+                engine.step();
+            }
+        });
+        scene.digest().await
+    }
+
+    #[tokio::test]
+    async fn thread_count_does_not_change_the_world() {
+        let one = run(7, 200, 1).await;
+        assert_eq!(one, run(7, 200, 4).await);
+        assert_ne!(one, run(8, 200, 4).await);
+    }
+}
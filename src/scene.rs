@@ -1,69 +1,136 @@
-use dashmap::DashMap;
-use std::sync::atomic::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use crate::clock::Clock;
 use crate::common::coord::Coord;
+use crate::config::Config;
 use crate::dots::Cell;
+use crate::grid::Grid;
+use crate::layer::Layer;
+use crate::lineage::Ancestry;
+
+/// A dot as the viewer draws it: centre x, centre y, half-size, (rgb, alpha).
+pub type Sprite = (f64, f64, f64, ([f32; 3], f32));
 
 pub struct Scene {
+    config: Arc<Config>,
     size: Coord,
     scale: u8,
-    // Sharded concurrent map: the structure is fixed after startup, so reads
-    // (at / describe) take only a per-shard lock instead of one scene-wide lock.
-    dots: Arc<DashMap<Coord, Arc<Cell>>>,
+    // chosen by `world.storage`; dense unless the map is asked for
+    dots: Box<dyn Grid>,
+    /// Dot ticks processed so far, across every ticker.
+    pub ticks: AtomicU64,
+    /// Successful fertilisations so far.
+    pub births: AtomicU64,
+    /// Dots that starved to zero energy so far.
+    pub deaths: AtomicU64,
+    /// Dots removed by edits so far: killed, walled over or replaced by an
+    /// injected one.
+    pub killed: AtomicU64,
+    pub ancestry: Ancestry,
+    /// Simulation time for the async engine's tickers.
+    pub clock: Clock,
 }
 
 impl Scene {
-    pub fn new(size: Coord, scale: u8) -> Scene {
+    /// An empty scene sized by `config.world`.
+    pub fn new(config: Arc<Config>) -> Scene {
+        let size = Coord::new(config.world.width as i32, config.world.height as i32);
         Scene {
             size,
-            scale,
-            dots: Arc::new(DashMap::new()),
+            scale: config.world.scale,
+            dots: config.world.storage.grid(size),
+            config,
+            ticks: AtomicU64::new(0),
+            births: AtomicU64::new(0),
+            deaths: AtomicU64::new(0),
+            killed: AtomicU64::new(0),
+            ancestry: Ancestry::default(),
+            clock: Clock::default(),
         }
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn size(&self) -> Coord {
+        self.size
+    }
+
+    /// The world's extent in the pixels `describe` places sprites in.
+    pub fn world_pixels(&self) -> [f64; 2] {
+        [self.size.x as f64 * self.scale as f64, self.size.y as f64 * self.scale as f64]
+    }
+
+    /// Every cell in raster order (row by row), for callers that need a
+    /// stable iteration order whatever the storage backend.
+    pub fn cells(&self) -> Vec<(Coord, Arc<Cell>)> {
+        let mut cells = Vec::new();
+        self.dots.for_each(&mut |pos, cell| cells.push((pos, cell.clone())));
+        cells.sort_by_key(|(pos, _)| pos.index(self.size));
+        cells
+    }
+
+    /// The cell `pos` falls in under the world's topology, or None past the
+    /// edge of a bounded world.
+    pub fn resolve(&self, pos: Coord) -> Option<Coord> {
+        self.config.world.topology.resolve(pos, self.size)
+    }
+
     pub fn at(&self, pos: Coord) -> Option<Arc<Cell>> {
-        self.dots.get(&pos).map(|cell| cell.value().clone())
+        self.dots.get(self.resolve(pos)?)
     }
 
     /// Lock-free perception: a neighbour's phenotype snapshot (how it presents
     /// to others), or 0 (an off-grid void) if no cell exists there. No dot is
-    /// locked and no Arc is cloned — just a shard read and an atomic load.
+    /// locked and no Arc is cloned — just a grid read and an atomic load.
     pub fn sense(&self, pos: Coord) -> u32 {
-        match self.dots.get(&pos) {
-            Some(cell) => cell.value().sense.load(Ordering::Relaxed),
-            None => 0,
+        let mut sense = 0;
+        if let Some(pos) = self.resolve(pos) {
+            self.dots.peek(pos, &mut |cell| sense = cell.sense.load(Ordering::Relaxed));
         }
+        sense
     }
 
     pub fn push_dot(&self, pos: Coord, cell: Arc<Cell>) {
-        if 0.0 > pos.x || pos.x >= self.size.x || 0.0 > pos.y || pos.y >= self.size.y {
+        if pos.index(self.size).is_none() {
             return;
         }
-        self.dots.entry(pos).or_insert(cell);
+        self.dots.insert(pos, cell);
+    }
+
+    /// A hash of the full simulation state of every cell, in raster order.
+    /// Two runs from the same seed under the step engine give the same digest.
+    pub async fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (_, cell) in self.cells() {
+            cell.dot.lock().await.hash_state(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Every cell as the viewer draws it by default, on the genome layer.
+    pub fn describe(&self) -> Vec<Sprite> {
+        self.describe_as(Layer::Genome)
     }
 
-    pub fn describe(&self) -> Vec<(f64, f64, f64, ([f32; 3], f32))> {
-        // Lock-free: read each cell's packed render snapshot and take the
-        // position from the map key. No dot is locked, so rendering never
-        // contends with the simulation.
-        let mut ret = Vec::with_capacity(self.dots.len());
-        for cell in self.dots.iter() {
-            let pos = cell.key();
-            let packed = cell.value().render.load(Ordering::Relaxed);
-            let rgb = [
-                ((packed >> 24) & 0xff) as f32 / 255.0,
-                ((packed >> 16) & 0xff) as f32 / 255.0,
-                ((packed >> 8) & 0xff) as f32 / 255.0,
-            ];
-            let alpha = (packed & 0xff) as f32 / 255.0;
+    pub fn describe_as(&self, layer: Layer) -> Vec<Sprite> {
+        // Lock-free: read each cell's packed snapshots and take the position
+        // from the grid. No dot is locked, so rendering never contends with
+        // the simulation. Raster order with the dense backend.
+        let mut ret = Vec::with_capacity(self.size.x as usize * self.size.y as usize);
+        let scale = self.scale as f64;
+        self.dots.for_each(&mut |pos, cell| {
             ret.push((
-                pos.x * self.scale as f64 + 0.5 * self.scale as f64,
-                pos.y * self.scale as f64 + 0.5 * self.scale as f64,
-                0.5 * self.scale as f64,
-                (rgb, alpha),
+                (pos.x as f64 + 0.5) * scale,
+                (pos.y as f64 + 0.5) * scale,
+                0.5 * scale,
+                layer.paint(cell, &self.config),
             ));
-        }
+        });
         ret
     }
 }
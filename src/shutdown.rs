@@ -0,0 +1,168 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::bus::Bus;
+use crate::scene::Scene;
+
+/// A task's view of one stage of the shutdown.
+#[derive(Clone)]
+pub struct Token(watch::Receiver<bool>);
+
+impl Token {
+    /// A token nothing will cancel, for cells driven by a synchronous engine.
+    pub fn never() -> Token {
+        Token(watch::channel(false).1)
+    }
+
+    /// Resolves once the stage is cancelled. A token whose `Shutdown` is gone
+    /// can't be cancelled any more, so it never resolves.
+    pub async fn cancelled(&mut self) {
+        if self.0.wait_for(|&cancelled| cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// What `Shutdown::run` stopped and finished off.
+#[derive(Debug)]
+pub struct Report {
+    pub tickers: usize,
+    pub propagators: usize,
+    /// Effects applied after the tickers stopped.
+    pub drained: u64,
+    /// Effects still on the bus, which a snapshot keeps.
+    pub queued: usize,
+    pub elapsed: Duration,
+}
+
+/// Coordinates stopping the async engine's tasks without tearing a dot down
+/// mid-mutation or losing an effect in flight.
+///
+/// Tickers stop first, each between two ticks, while the propagators keep
+/// draining; then every propagator stops the next time it finds the bus
+/// empty. Whatever a ticker or propagator holds when it is told to stop, it
+/// finishes first. Afterwards nothing touches the scene, so it can be saved.
+pub struct Shutdown {
+    tickers: watch::Sender<bool>,
+    propagators: watch::Sender<bool>,
+    handles: std::sync::Mutex<Vec<JoinHandle<()>>>,
+    requested: AtomicBool,
+}
+
+impl Default for Shutdown {
+    fn default() -> Shutdown {
+        Shutdown {
+            tickers: watch::channel(false).0,
+            propagators: watch::channel(false).0,
+            handles: std::sync::Mutex::new(Vec::new()),
+            requested: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn tickers(&self) -> Token {
+        Token(self.tickers.subscribe())
+    }
+
+    pub fn propagators(&self) -> Token {
+        Token(self.propagators.subscribe())
+    }
+
+    /// Keep a propagator's handle so `run` can wait for it.
+    pub fn track(&self, handle: JoinHandle<()>) {
+        self.handles.lock().unwrap().push(handle);
+    }
+
+    /// Ask the run loop to end, e.g. on Ctrl-C. Only a flag: the loop that
+    /// owns the world notices it and calls `run`.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Stop every task, in order, and wait for each to finish.
+    pub async fn run(&self, scene: &Scene, bus: &Bus) -> Report {
+        let start = Instant::now();
+        let delivered = bus.metrics().delivered;
+
+        self.tickers.send_replace(true);
+        let mut tickers = 0;
+        for (_, cell) in scene.cells() {
+            let handle = cell.dot.lock().await.task_tick.take();
+            if let Some(handle) = handle {
+                let _ = handle.await;
+                tickers += 1;
+            }
+        }
+
+        self.propagators.send_replace(true);
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        let propagators = handles.len();
+        for handle in handles {
+            let _ = handle.await;
+        }
+
+        let metrics = bus.metrics();
+        Report {
+            tickers,
+            propagators,
+            drained: metrics.delivered - delivered,
+            queued: metrics.depth,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::coord::Coord;
+    use crate::config::Config;
+    use crate::dots::DotFactory;
+    use crate::effect::Effect;
+    use rand::prelude::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn stops_every_task_and_drains_the_bus() {
+        let bus = Arc::new(Bus::unbounded());
+        let mut config = Config::default();
+        (config.world.width, config.world.height) = (9, 6);
+        let scene = Arc::new(Scene::new(Arc::new(config)));
+        let shutdown = Shutdown::default();
+        let factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(0))
+            .stopped_by(shutdown.tickers());
+        factory.populate(&mut StdRng::seed_from_u64(1), true).await;
+
+        // an effect nobody takes until the propagator starts draining
+        let stray = (Coord::new(2, 2), Arc::new(Effect::ENERGY(0.1, None, None)));
+        bus.send(stray).await;
+        let mut stop = shutdown.propagators();
+        let propagator = {
+            let bus = bus.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        biased;
+                        _ = bus.recv() => {}
+                        () = stop.cancelled() => break,
+                    }
+                }
+            })
+        };
+        shutdown.track(propagator);
+
+        let report = shutdown.run(&scene, &bus).await;
+        assert_eq!((report.tickers, report.propagators), (54, 1));
+        assert_eq!(report.queued, 0);
+        let ticks = scene.ticks.load(Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(scene.ticks.load(Ordering::Relaxed), ticks, "no ticker outlives the shutdown");
+    }
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bus::Bus;
+use crate::common::coord::Coord;
+use crate::common::dna::{Dna, SIZE};
+use crate::config::Genetics;
+use crate::dots::{DotFactory, DotState};
+use crate::effect::Effect;
+use crate::lineage::{Graph, Lineage};
+use crate::scene::Scene;
+
+/// Bumped whenever the layout below changes, even by an added field or
+/// variant, or saved genomes would decode differently (as when a gene takes
+/// over padding bits); `load` refuses any other.
+pub const VERSION: u32 = 4;
+
+/// A whole world on disk (JSON): the grid size, every cell's state, the
+/// effects still queued in the channel when it was taken and the ancestry
+/// graph, with the running totals and simulation time a resumed run carries
+/// on from.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub size: Coord,
+    pub ticks: u64,
+    pub births: u64,
+    pub deaths: u64,
+    pub killed: u64,
+    /// Simulation time when the snapshot was taken.
+    pub time_ms: u64,
+    pub cells: Vec<DotState>,
+    pub effects: Vec<(Coord, SavedEffect)>,
+    pub ancestry: Graph,
+}
+
+/// An `Effect` as stored: a genome by its sequence only, decoded again (with
+/// the current genetics) on restore.
+// only built in bulk while saving or loading, so the size spread is fine
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+pub enum SavedEffect {
+    ENERGY(f32, Option<[f32; 3]>, Option<Coord>),
+    SEED([u64; SIZE], f32, Lineage),
+    KILL,
+    INJECT([u64; SIZE], f32),
+    WALL(bool),
+}
+
+impl SavedEffect {
+    fn new(effect: &Effect) -> SavedEffect {
+        match *effect {
+            Effect::ENERGY(eff, mask, pos) => SavedEffect::ENERGY(eff, mask, pos),
+            Effect::SEED(dna, provision) => SavedEffect::SEED(dna.seq, provision, dna.lineage),
+            Effect::KILL => SavedEffect::KILL,
+            Effect::INJECT(dna, energy) => SavedEffect::INJECT(dna.seq, energy),
+            Effect::WALL(solid) => SavedEffect::WALL(solid),
+        }
+    }
+
+    fn effect(&self, genetics: &Genetics) -> Effect {
+        match *self {
+            SavedEffect::ENERGY(eff, mask, pos) => Effect::ENERGY(eff, mask, pos),
+            SavedEffect::SEED(seq, provision, lineage) => {
+                Effect::SEED(Dna { lineage, ..Dna::new(seq, genetics) }, provision)
+            }
+            SavedEffect::KILL => Effect::KILL,
+            SavedEffect::INJECT(seq, energy) => Effect::INJECT(Dna::new(seq, genetics), energy),
+            SavedEffect::WALL(solid) => Effect::WALL(solid),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Capture a running world. Queued effects are drained to be recorded,
+    /// then sent again so the run carries on. Under the async engine dots keep
+    /// ticking while they are locked one by one, so the picture is consistent
+    /// per cell rather than a single instant; the step engine is idle between
+    /// steps, so there it is exact. `time_ms` is the engine's simulation time.
+    pub async fn capture(scene: &Scene, bus: &Bus, time_ms: u64) -> Snapshot {
+        let queued = bus.drain();
+        let mut cells = Vec::new();
+        for (_, cell) in scene.cells() {
+            cells.push(cell.dot.lock().await.state());
+        }
+        let effects = queued.iter().map(|(pos, effect)| (*pos, SavedEffect::new(effect))).collect();
+        for effect in queued {
+            bus.send(effect).await;
+        }
+        Snapshot {
+            version: VERSION,
+            size: scene.size(),
+            ticks: scene.ticks.load(Ordering::Relaxed),
+            births: scene.births.load(Ordering::Relaxed),
+            deaths: scene.deaths.load(Ordering::Relaxed),
+            killed: scene.killed.load(Ordering::Relaxed),
+            time_ms,
+            cells,
+            effects,
+            ancestry: scene.ancestry.save(),
+        }
+    }
+
+    /// Rebuild the world into an empty `scene` of `self.size`: the totals and
+    /// the clock, a dot for every saved cell (with its ticker started if
+    /// `tick`), then the saved effects queued again. The synchronous engines
+    /// keep their own time, so are started at `time_ms` by the caller.
+    /// Consumes the snapshot to hand over its ancestry graph.
+    pub async fn restore(
+        self,
+        scene: &Scene,
+        factory: &DotFactory,
+        bus: &Bus,
+        tick: bool,
+    ) {
+        scene.ticks.store(self.ticks, Ordering::Relaxed);
+        scene.births.store(self.births, Ordering::Relaxed);
+        scene.deaths.store(self.deaths, Ordering::Relaxed);
+        scene.killed.store(self.killed, Ordering::Relaxed);
+        scene.clock.set(Duration::from_millis(self.time_ms));
+        scene.ancestry.restore(self.ancestry);
+        for state in &self.cells {
+            let cell = factory.restore(state);
+            if tick {
+                factory.start(&cell).await;
+            }
+            scene.push_dot(state.pos, cell);
+        }
+        let genetics = &scene.config().genetics;
+        for (pos, effect) in &self.effects {
+            bus.send((*pos, Arc::new(effect.effect(genetics)))).await;
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let snapshot: Snapshot = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if snapshot.version != VERSION {
+            return Err(format!(
+                "{}: snapshot version {} (expected {})",
+                path.display(),
+                snapshot.version,
+                VERSION
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::stepper::{Driver, Stepper};
+    use rand::prelude::*;
+
+    #[tokio::test]
+    async fn round_trip_rebuilds_the_same_world() {
+        let mut config = Config::default();
+        (config.world.width, config.world.height) = (18, 18);
+        let config = Arc::new(config);
+        let bus = Arc::new(Bus::unbounded());
+        let scene = Arc::new(Scene::new(config.clone()));
+        let mut rng = StdRng::seed_from_u64(1);
+        let factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(rng.gen()));
+        factory.populate(&mut rng, false).await;
+        let mut stepper = Stepper::new(scene.clone(), bus.clone());
+        for _ in 0..200 {
+            stepper.step();
+        }
+        bus.send((Coord::new(1, 1), Arc::new(Effect::ENERGY(0.1, None, None)))).await;
+
+        let snapshot = Snapshot::capture(&scene, &bus, stepper.now()).await;
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let loaded: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.effects.len(), 1, "queued effects are kept");
+        assert_eq!(bus.metrics().depth, 1, "and left queued for the running world");
+
+        let bus2 = Arc::new(Bus::unbounded());
+        let restored = Arc::new(Scene::new(config));
+        let factory = DotFactory::new(bus2.clone(), restored.clone(), StdRng::seed_from_u64(0));
+        loaded.restore(&restored, &factory, &bus2, false).await;
+        assert_eq!(restored.digest().await, scene.digest().await);
+        assert!(scene.ancestry.len() > 0);
+        assert_eq!(restored.ancestry.len(), scene.ancestry.len());
+        assert_eq!(bus2.metrics().depth, 1);
+
+        // the totals and time carry on rather than restarting at 0
+        let births = |s: &Scene| s.births.load(Ordering::Relaxed);
+        assert!(births(&scene) > 0);
+        assert_eq!(births(&restored), births(&scene));
+        assert_eq!(restored.deaths.load(Ordering::Relaxed), scene.deaths.load(Ordering::Relaxed));
+        let clock = restored.clock.now().as_millis() as u64;
+        assert!((stepper.now()..stepper.now() + 1000).contains(&clock), "the clock runs on");
+        let mut resumed = Stepper::new(restored.clone(), bus2).starting_at(stepper.now());
+        assert!(resumed.step() && resumed.now() >= stepper.now());
+    }
+}
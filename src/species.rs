@@ -0,0 +1,136 @@
+use serde::Serialize;
+
+use crate::common::dna::{hamming, SIZE};
+
+struct Species {
+    id: u64,
+    // the genome members are measured against; moves with the population
+    representative: [u64; SIZE],
+}
+
+/// What changed between two classifications.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// (species id, members), by id.
+    pub sizes: Vec<(u64, usize)>,
+    pub emerged: Vec<u64>,
+    pub extinct: Vec<u64>,
+}
+
+/// Groups genomes into species by Hamming distance over `seq`, keeping
+/// species ids across successive samples of the population.
+///
+/// Each genome joins the nearest species whose representative is within
+/// `threshold` bits (ties to the older species) or else founds a new one.
+/// A surviving species then takes its member closest to the old
+/// representative as the new one, so it follows its lineage's drift; a
+/// species left without members is extinct.
+pub struct Tracker {
+    threshold: u32,
+    next_id: u64,
+    species: Vec<Species>,
+}
+
+impl Tracker {
+    pub fn new(threshold: u32) -> Tracker {
+        Tracker {
+            threshold,
+            next_id: 1,
+            species: Vec::new(),
+        }
+    }
+
+    /// Classify a whole population. Returns the species id of each genome, in
+    /// order, and what changed since the last call.
+    pub fn classify(&mut self, genomes: &[[u64; SIZE]]) -> (Vec<u64>, Report) {
+        let known = self.species.len();
+        // per species: members, and the closest member to the old representative
+        let mut members = vec![0usize; known];
+        let mut closest: Vec<Option<(u32, usize)>> = vec![None; known];
+        let mut ids = Vec::with_capacity(genomes.len());
+
+        for (g, seq) in genomes.iter().enumerate() {
+            let mut best: Option<(u32, usize)> = None;
+            for (s, species) in self.species.iter().enumerate() {
+                let d = hamming(seq, &species.representative);
+                if d <= self.threshold && best.is_none_or(|(bd, _)| d < bd) {
+                    best = Some((d, s));
+                }
+            }
+            let s = match best {
+                Some((d, s)) => {
+                    if s < known && closest[s].is_none_or(|(cd, _)| d < cd) {
+                        closest[s] = Some((d, g));
+                    }
+                    s
+                }
+                None => {
+                    self.species.push(Species {
+                        id: self.next_id,
+                        representative: *seq,
+                    });
+                    self.next_id += 1;
+                    members.push(0);
+                    self.species.len() - 1
+                }
+            };
+            members[s] += 1;
+            ids.push(self.species[s].id);
+        }
+
+        let mut report = Report::default();
+        let mut survivors = Vec::with_capacity(self.species.len());
+        for (s, mut species) in self.species.drain(..).enumerate() {
+            if members[s] == 0 {
+                report.extinct.push(species.id);
+                continue;
+            }
+            if s >= known {
+                report.emerged.push(species.id);
+            } else if let Some((_, g)) = closest[s] {
+                species.representative = genomes[g];
+            }
+            report.sizes.push((species.id, members[s]));
+            survivors.push(species);
+        }
+        self.species = survivors;
+        (ids, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flip(seq: [u64; SIZE], bits: usize) -> [u64; SIZE] {
+        let mut out = seq;
+        for b in 0..bits {
+            out[b / 64] ^= 1 << (b % 64);
+        }
+        out
+    }
+
+    #[test]
+    fn clusters_persist_emerge_and_die_out() {
+        let a = [0u64; SIZE];
+        let b = [u64::MAX; SIZE];
+        let mut t = Tracker::new(16);
+
+        let (ids, report) = t.classify(&[a, flip(a, 3), b]);
+        assert_eq!(ids, vec![1, 1, 2]);
+        assert_eq!(report.emerged, vec![1, 2]);
+        assert_eq!(report.sizes, vec![(1, 2), (2, 1)]);
+
+        // species 1 drifts but keeps its id; species 2 dies out; a new one appears
+        let c = flip(b, 700);
+        let (ids, report) = t.classify(&[flip(a, 10), c]);
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(report.emerged, vec![3]);
+        assert_eq!(report.extinct, vec![2]);
+        assert_eq!(report.sizes, vec![(1, 1), (3, 1)]);
+
+        // the representative moved with the drift, so further drift still matches
+        let (ids, _) = t.classify(&[flip(a, 24)]);
+        assert_eq!(ids, vec![1]);
+    }
+}
@@ -0,0 +1,377 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::bus::{Bus, Metrics};
+use crate::scene::Scene;
+use crate::species::{Report, Tracker};
+
+/// How many of the latest samples a `Recorder` keeps for the HUD's graphs.
+pub const HISTORY: usize = 300;
+
+/// Heritable traits whose distribution is sampled, in column order.
+pub const TRAITS: [&str; 9] = [
+    "color_r",
+    "color_g",
+    "color_b",
+    "digest_r",
+    "digest_g",
+    "digest_b",
+    "reaction_time_ms",
+    "seed_invest",
+    "mutation_rate",
+];
+
+/// Where `mutation_rate` is in `TRAITS`, and so in `Sample::traits`.
+pub const MUTATION_RATE: usize = 8;
+
+/// Mean and standard deviation of one trait over the live population.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Moments {
+    pub mean: f64,
+    pub std: f64,
+}
+
+/// One sample of the whole scene. `births` (successful fertilisations),
+/// `deaths` (dots starving to zero energy) and `killed` (dots removed by
+/// edits) are running totals since the scene was created.
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    pub tick: u64,
+    /// Simulation time of the sample: wall-clock under the async engine,
+    /// virtual under the step engine.
+    pub time_ms: u64,
+    pub cells: usize,
+    /// Cells carrying a genome (fertile or colonised).
+    pub population: usize,
+    pub empty: usize,
+    pub total_energy: f64,
+    pub mean_energy: f64,
+    pub births: u64,
+    pub deaths: u64,
+    pub killed: u64,
+    /// Mean age of the population.
+    pub mean_age: f64,
+    pub species: usize,
+    /// The effect bus at the time of the sample.
+    pub bus: Metrics,
+    /// Effects delivered per second of simulation time, and the mean time
+    /// they spent queued in milliseconds, since the previous sample.
+    pub effects_per_s: f64,
+    pub latency_ms: f64,
+    /// Indexed like `TRAITS`.
+    pub traits: [Moments; TRAITS.len()],
+}
+
+impl Sample {
+    /// Lock each dot in turn and summarise it, so the sample is consistent
+    /// per cell but not a single instant of the whole grid. The population is
+    /// classified into species by `tracker`, whose report is returned too, and
+    /// each cell's species published in `Cell::species` for the species layer.
+    /// Effect rates are over the whole run until `since` is called.
+    pub async fn take(
+        scene: &Scene,
+        bus: &Bus,
+        time_ms: u64,
+        tracker: &mut Tracker,
+    ) -> (Sample, Report) {
+        let cells = scene.cells();
+        let mut sample = Sample {
+            tick: scene.ticks.load(Ordering::Relaxed),
+            time_ms,
+            bus: bus.metrics(),
+            cells: cells.len(),
+            births: scene.births.load(Ordering::Relaxed),
+            deaths: scene.deaths.load(Ordering::Relaxed),
+            killed: scene.killed.load(Ordering::Relaxed),
+            ..Sample::default()
+        };
+        let mut sums = [(0.0f64, 0.0f64); TRAITS.len()];
+        let mut age = 0.0;
+        let mut genomes = Vec::new();
+        let mut living = Vec::new();
+        for (_, cell) in cells {
+            let dot = cell.dot.lock().await;
+            sample.total_energy += dot.energy() as f64;
+            let Some(dna) = dot.dna else {
+                sample.empty += 1;
+                cell.species.store(0, Ordering::Relaxed);
+                continue;
+            };
+            sample.population += 1;
+            age += dot.age() as f64;
+            genomes.push(dna.seq);
+            living.push(cell.clone());
+            let values = [
+                dna.color[0],
+                dna.color[1],
+                dna.color[2],
+                dna.digest_mask[0],
+                dna.digest_mask[1],
+                dna.digest_mask[2],
+                dna.reaction_time.as_millis() as f32,
+                dna.seed_invest,
+                dna.mutation_rate,
+            ];
+            for (sum, v) in sums.iter_mut().zip(values) {
+                sum.0 += v as f64;
+                sum.1 += v as f64 * v as f64;
+            }
+        }
+        sample.mean_energy = sample.total_energy / sample.cells.max(1) as f64;
+        if sample.population > 0 {
+            let n = sample.population as f64;
+            sample.mean_age = age / n;
+            for (m, (sum, sq)) in sample.traits.iter_mut().zip(sums) {
+                m.mean = sum / n;
+                m.std = (sq / n - m.mean * m.mean).max(0.0).sqrt();
+            }
+        }
+        let (ids, report) = tracker.classify(&genomes);
+        for (cell, id) in living.iter().zip(ids) {
+            cell.species.store(id, Ordering::Relaxed);
+        }
+        sample.species = report.sizes.len();
+        sample.since(&Sample::default());
+        (sample, report)
+    }
+
+    /// Measure the effect rates from an earlier sample to this one.
+    pub fn since(&mut self, earlier: &Sample) {
+        let delivered = self.bus.delivered - earlier.bus.delivered;
+        let secs = self.time_ms.saturating_sub(earlier.time_ms) as f64 / 1000.0;
+        self.effects_per_s = if secs > 0.0 { delivered as f64 / secs } else { 0.0 };
+        self.latency_ms = match delivered {
+            0 => 0.0,
+            n => (self.bus.latency_us - earlier.bus.latency_us) as f64 / n as f64 / 1000.0,
+        };
+    }
+
+    fn columns() -> Vec<String> {
+        let mut cols: Vec<String> = [
+            "tick",
+            "time_ms",
+            "cells",
+            "population",
+            "empty",
+            "total_energy",
+            "mean_energy",
+            "births",
+            "deaths",
+            "killed",
+            "mean_age",
+            "species",
+            "queue_depth",
+            "effects",
+            "effects_dropped",
+            "effects_per_s",
+            "latency_ms",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        for t in TRAITS {
+            cols.push(format!("{}_mean", t));
+            cols.push(format!("{}_std", t));
+        }
+        cols
+    }
+
+    fn values(&self) -> Vec<serde_json::Value> {
+        let mut vals: Vec<serde_json::Value> = vec![
+            self.tick.into(),
+            self.time_ms.into(),
+            self.cells.into(),
+            self.population.into(),
+            self.empty.into(),
+            self.total_energy.into(),
+            self.mean_energy.into(),
+            self.births.into(),
+            self.deaths.into(),
+            self.killed.into(),
+            self.mean_age.into(),
+            self.species.into(),
+            self.bus.depth.into(),
+            self.bus.delivered.into(),
+            self.bus.dropped.into(),
+            self.effects_per_s.into(),
+            self.latency_ms.into(),
+        ];
+        for m in &self.traits {
+            vals.push(m.mean.into());
+            vals.push(m.std.into());
+        }
+        vals
+    }
+}
+
+/// Output format, chosen by the file extension: `.csv` or JSON Lines.
+enum Format {
+    Csv,
+    JsonLines,
+}
+
+/// Takes a `Sample` every `every_ms` of simulation time, keeps the latest
+/// `HISTORY` of them for the HUD and appends each to the stats file and/or
+/// the species file (one JSON line of sizes, emergences and extinctions per
+/// sample). Both are flushed per sample so a run can be plotted while it is
+/// going.
+pub struct Recorder {
+    bus: Arc<Bus>,
+    every_ms: u64,
+    next_ms: u64,
+    tracker: Tracker,
+    stats: Option<(BufWriter<File>, Format)>,
+    species: Option<BufWriter<File>>,
+    history: VecDeque<Sample>,
+}
+
+#[derive(Serialize)]
+struct SpeciesLine<'a> {
+    tick: u64,
+    time_ms: u64,
+    #[serde(flatten)]
+    report: &'a Report,
+}
+
+impl Recorder {
+    pub fn new(bus: Arc<Bus>, every_ms: u64, species_threshold: u32) -> Recorder {
+        Recorder {
+            bus,
+            every_ms,
+            next_ms: 0,
+            tracker: Tracker::new(species_threshold),
+            stats: None,
+            species: None,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    pub fn stats_to(mut self, path: &Path) -> Result<Recorder, String> {
+        let mut out = create(path)?;
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Format::Csv,
+            _ => Format::JsonLines,
+        };
+        if let Format::Csv = format {
+            let header = Sample::columns().join(",");
+            writeln!(out, "{}", header).map_err(|e| e.to_string())?;
+        }
+        self.stats = Some((out, format));
+        Ok(self)
+    }
+
+    pub fn species_to(mut self, path: &Path) -> Result<Recorder, String> {
+        self.species = Some(create(path)?);
+        Ok(self)
+    }
+
+    /// Take a sample now, outside the schedule and without writing it, with
+    /// the same species tracker, for a summary at the end of a run.
+    pub async fn sample(&mut self, scene: &Scene, time_ms: u64) -> Sample {
+        Sample::take(scene, &self.bus, time_ms, &mut self.tracker).await.0
+    }
+
+    /// The latest samples, oldest first.
+    pub fn history(&self) -> &VecDeque<Sample> {
+        &self.history
+    }
+
+    /// Sample the scene if `time_ms` has reached the next sampling time, and
+    /// say whether it did. A file that can't be written is closed, and the
+    /// error returned, but sampling carries on.
+    pub async fn poll(&mut self, scene: &Scene, time_ms: u64) -> Result<bool, String> {
+        if time_ms < self.next_ms {
+            return Ok(false);
+        }
+        self.next_ms = time_ms - time_ms % self.every_ms + self.every_ms;
+        let (mut sample, report) = Sample::take(scene, &self.bus, time_ms, &mut self.tracker).await;
+        if let Some(last) = self.history.back() {
+            sample.since(last);
+        }
+        let written = self.write(&sample, &report);
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+        written.map(|()| true).map_err(|e| {
+            (self.stats, self.species) = (None, None);
+            e.to_string()
+        })
+    }
+
+    fn write(&mut self, sample: &Sample, report: &Report) -> std::io::Result<()> {
+        if let Some((out, format)) = &mut self.stats {
+            match format {
+                Format::Csv => {
+                    let row: Vec<String> = sample.values().iter().map(|v| v.to_string()).collect();
+                    writeln!(out, "{}", row.join(","))?;
+                }
+                Format::JsonLines => {
+                    let obj: serde_json::Map<String, serde_json::Value> =
+                        Sample::columns().into_iter().zip(sample.values()).collect();
+                    writeln!(out, "{}", serde_json::Value::Object(obj))?;
+                }
+            }
+            out.flush()?;
+        }
+        if let Some(out) = &mut self.species {
+            let line = SpeciesLine {
+                tick: sample.tick,
+                time_ms: sample.time_ms,
+                report,
+            };
+            writeln!(out, "{}", serde_json::to_string(&line)?)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>, String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::coord::Coord;
+    use crate::common::dna::Dna;
+    use crate::config::Config;
+    use crate::dots::DotFactory;
+    use rand::prelude::*;
+
+    #[tokio::test]
+    async fn sample_counts_population_and_traits() {
+        let mut config = Config::default();
+        (config.world.width, config.world.height) = (4, 4);
+        let scene = Arc::new(Scene::new(Arc::new(config)));
+        let bus = Arc::new(Bus::unbounded());
+        let factory = DotFactory::new(bus.clone(), scene.clone(), StdRng::seed_from_u64(0));
+        let seq = [0x5555_aaaa_5555_aaaa_u64; crate::common::dna::SIZE];
+        for x in 0..4 {
+            for y in 0..4 {
+                let pos = Coord::new(x, y);
+                let cell = factory.build(pos, (x == 0).then_some(seq), 0.5);
+                scene.push_dot(pos, cell);
+            }
+        }
+
+        let (sample, report) = Sample::take(&scene, &bus, 0, &mut Tracker::new(0)).await;
+        assert_eq!((sample.cells, sample.population, sample.empty), (16, 4, 12));
+        assert_eq!(sample.species, 1);
+        assert_eq!(report.sizes, vec![(1, 4)]);
+        assert!((sample.mean_energy - 0.5).abs() < 1e-9);
+        // identical genomes: every trait has zero spread
+        assert!(sample.traits.iter().all(|m| m.std < 1e-6));
+        assert_eq!(TRAITS[MUTATION_RATE], "mutation_rate");
+        let rate = sample.traits[MUTATION_RATE].mean as f32;
+        assert_eq!(rate, Dna::new(seq, &scene.config().genetics).mutation_rate);
+        assert_eq!(Sample::columns().len(), sample.values().len());
+    }
+}